@@ -0,0 +1,51 @@
+#[macro_use] extern crate serde_derive;
+#[macro_use] extern crate qube_derive;
+extern crate qube;
+use qube::prelude::*;
+use qube::errors::*;
+use std::env;
+use std::time::Duration;
+
+/// A scheduled backup, served by a CRD of the `example.com` group installed on start
+#[derive(CustomResource, JsonSchema, Serialize, Deserialize, Clone, Debug)]
+#[resource(group = "example.com", version = "v1", kind = "Backup", namespaced, status = "BackupStatus")]
+pub struct BackupSpec {
+    /// When to run the backup, in cron syntax
+    pub schedule: String,
+}
+
+#[derive(JsonSchema, Serialize, Deserialize, Clone, Debug)]
+pub struct BackupStatus {
+    pub last_run: Option<String>,
+}
+
+fn run_main() -> Result<i32> {
+    // filename is set to $KUBECONFIG if the env var is available.
+    // Otherwise it falls back to "admin.conf".
+    let filename = env::var("KUBECONFIG").ok();
+    let filename = filename
+        .as_deref()
+        .and_then(|s| if s.is_empty() { None } else { Some(s) })
+        .unwrap_or("admin.conf");
+    let kube = Kubernetes::load_conf(filename)?;
+
+    kube.ensure_crd::<Backup>(Duration::from_secs(30))?;
+
+    let backups = kube.client::<Backup>();
+    if !backups.exists("nightly")? {
+        let backup = Backup::new("nightly", BackupSpec { schedule: "0 3 * * *".to_owned() });
+        backups.create(&backup)?;
+    }
+    for backup in backups.list(None)? {
+        println!("found backup: {:?}", backup);
+    }
+
+    Ok(0)
+}
+
+fn main() {
+    match run_main() {
+        Ok(n) => println!("Success error code is {}", n),
+        Err(e) => println!("Error: {}", e),
+    }
+}
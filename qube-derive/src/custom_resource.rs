@@ -0,0 +1,227 @@
+use crate::json_schema::{serde_attributes, serialized_name};
+use proc_macro2::{Span, TokenStream};
+use syn::{Data, DeriveInput, Error, Ident, LitStr, Type};
+
+#[derive(Default)]
+struct Attributes {
+    group: Option<String>,
+    version: Option<String>,
+    kind: Option<String>,
+    plural: Option<String>,
+    namespaced: bool,
+    status: Option<Type>,
+    scale: Option<Ident>,
+}
+
+fn parse_attributes(input: &DeriveInput) -> syn::Result<Attributes> {
+    let mut attrs = Attributes::default();
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("resource")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("group") {
+                attrs.group = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("version") {
+                attrs.version = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("kind") {
+                attrs.kind = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("plural") {
+                attrs.plural = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("namespaced") {
+                attrs.namespaced = true;
+            } else if meta.path.is_ident("status") {
+                attrs.status = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+            } else if meta.path.is_ident("scale") {
+                let field = if meta.input.peek(syn::Token![=]) {
+                    meta.value()?.parse::<LitStr>()?.parse()?
+                } else {
+                    Ident::new("ready_replicas", Span::call_site())
+                };
+                attrs.scale = Some(field);
+            } else {
+                return Err(meta.error("unknown resource attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(attrs)
+}
+
+// The path of the spec's `replicas` field, as serialized, which the `/scale` subresource scales
+fn spec_replicas_path(input: &DeriveInput) -> syn::Result<String> {
+    let container = serde_attributes(&input.attrs)?;
+    if let Data::Struct(ref data) = input.data {
+        for field in &data.fields {
+            if let Some(ref ident) = field.ident {
+                if ident == "replicas" {
+                    let name = serialized_name(ident, &serde_attributes(&field.attrs)?, container.rename_all.as_ref())?;
+                    return Ok(format!(".spec.{}", name));
+                }
+            }
+        }
+    }
+    Err(Error::new_spanned(&input.ident, "`scale` requires a `replicas: Option<i32>` field in the spec"))
+}
+
+fn required(value: Option<String>, name: &str) -> syn::Result<String> {
+    value.ok_or_else(|| Error::new(Span::call_site(), format!("missing `#[resource({} = \"...\")]`", name)))
+}
+
+pub fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    match input.data {
+        Data::Struct(_) => {}
+        _ => return Err(Error::new_spanned(&input.ident, "CustomResource can only be derived for a spec struct")),
+    }
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(&input.generics, "CustomResource specs can't be generic"));
+    }
+
+    let attrs = parse_attributes(input)?;
+    let group = required(attrs.group, "group")?;
+    let version = required(attrs.version, "version")?;
+    let kind = required(attrs.kind, "kind")?;
+    let plural = attrs.plural.unwrap_or_else(|| format!("{}s", kind.to_lowercase()));
+    let namespaced = attrs.namespaced;
+
+    let vis = &input.vis;
+    let spec = &input.ident;
+    let name: Ident = syn::parse_str(&kind)
+        .map_err(|_| Error::new(Span::call_site(), format!("`{}` is not a valid kind", kind)))?;
+    let list = format_ident!("{}List", name);
+    let doc = format!("The `{}` custom resource of `{}/{}`", kind, group, version);
+    let list_doc = format!("A list of `{}` resources", kind);
+
+    let (status_field, status_init, status_impl) = match attrs.status {
+        Some(ref status) => (
+            quote! {
+                #[serde(skip_serializing_if = "Option::is_none")]
+                pub status: Option<#status>,
+            },
+            quote! { status: None, },
+            quote! {
+                impl ::qube::resources::HasStatus for #name {
+                    type Status = #status;
+                    fn status(&self) -> Option<&Self::Status> { self.status.as_ref() }
+                    fn status_mut(&mut self) -> &mut Option<Self::Status> { &mut self.status }
+                }
+            },
+        ),
+        None => (quote!(), quote!(), quote!()),
+    };
+
+    let crd_status = match attrs.status {
+        Some(ref status) => quote! { .with_status(<#status as ::qube::schema::JsonSchema>::schema()) },
+        None => quote!(),
+    };
+    let crd_scale = match attrs.scale {
+        Some(_) => {
+            let spec_replicas = spec_replicas_path(input)?;
+            quote! { .with_scale(#spec_replicas, ".status.replicas") }
+        }
+        None => quote!(),
+    };
+
+    let scale_impl = match attrs.scale {
+        Some(ref field) if attrs.status.is_some() => quote! {
+            impl ::qube::resources::Scalable for #name {
+                fn ready_replicas(&self) -> i32 {
+                    self.status.as_ref().and_then(|status| status.#field).unwrap_or(0)
+                }
+            }
+        },
+        Some(_) => return Err(Error::new(Span::call_site(), "`scale` requires a `status`")),
+        None => quote!(),
+    };
+
+    Ok(quote! {
+        #[doc = #doc]
+        #[derive(Serialize, Deserialize, Clone, Debug)]
+        #vis struct #name {
+            #[serde(flatten)]
+            pub types: ::qube::resources::TypeMeta,
+
+            pub metadata: ::qube::resources::ObjectMeta,
+
+            pub spec: #spec,
+
+            #status_field
+        }
+
+        #[doc = #list_doc]
+        #[derive(Serialize, Deserialize, Debug, Default)]
+        #vis struct #list {
+            pub items: Vec<#name>,
+        }
+
+        impl #name {
+            /// A resource with the given name and spec, ready to be created
+            #vis fn new(name: &str, spec: #spec) -> #name {
+                #name {
+                    types: ::qube::resources::TypeMeta::of::<#name>(),
+                    metadata: ::qube::resources::ObjectMeta { name: Some(name.to_owned()), ..Default::default() },
+                    spec,
+                    #status_init
+                }
+            }
+        }
+
+        impl ::qube::resources::Resource for #name {
+            const GROUP: &'static str = #group;
+            const VERSION: &'static str = #version;
+            const KIND: &'static str = #kind;
+            const PLURAL: &'static str = #plural;
+            const NAMESPACED: bool = #namespaced;
+            fn metadata(&self) -> &::qube::resources::ObjectMeta { &self.metadata }
+            fn metadata_mut(&mut self) -> &mut ::qube::resources::ObjectMeta { &mut self.metadata }
+        }
+
+        impl ::qube::resources::ListableResource for #name {
+            type ListResponse = #list;
+            fn list_items(response: Self::ListResponse) -> Vec<Self> {
+                response.items
+            }
+            fn types_mut(&mut self) -> &mut ::qube::resources::TypeMeta { &mut self.types }
+        }
+
+        impl ::qube::resources::CustomResourceExt for #name {
+            fn crd() -> ::qube::resources::CustomResourceDefinition {
+                ::qube::resources::CustomResourceDefinition::for_resource::<Self>(
+                    <#spec as ::qube::schema::JsonSchema>::schema()
+                )
+                #crd_status
+                #crd_scale
+            }
+        }
+
+        #status_impl
+
+        #scale_impl
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spec_replicas_are_scaled_under_their_serialized_name() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[serde(rename_all = "PascalCase")]
+            struct WorkerSpec {
+                image: String,
+                replicas: Option<i32>,
+            }
+        };
+        assert_eq!(spec_replicas_path(&input).unwrap(), ".spec.Replicas");
+    }
+
+    #[test]
+    fn scaling_requires_spec_replicas() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[resource(group = "example.com", version = "v1", kind = "Worker", status = "WorkerStatus", scale)]
+            struct WorkerSpec {
+                image: String,
+            }
+        };
+        let error = expand(&input).unwrap_err();
+        assert!(error.to_string().contains("`replicas: Option<i32>`"));
+    }
+}
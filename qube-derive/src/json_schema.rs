@@ -0,0 +1,248 @@
+use proc_macro2::{Span, TokenStream};
+use syn::{Attribute, Data, DeriveInput, Error, Fields, GenericParam, LitStr};
+use syn::meta::ParseNestedMeta;
+
+// The serde attributes that change the shape of the serialized type
+#[derive(Default)]
+pub(crate) struct SerdeAttributes {
+    rename: Option<String>,
+    pub(crate) rename_all: Option<String>,
+    default: bool,
+    skip: bool,
+    flatten: bool,
+    skip_serializing_if: bool,
+}
+
+pub(crate) fn serde_attributes(attrs: &[Attribute]) -> syn::Result<SerdeAttributes> {
+    let mut serde = SerdeAttributes::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") && meta.input.peek(syn::Token![=]) {
+                serde.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("rename_all") && meta.input.peek(syn::Token![=]) {
+                serde.rename_all = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("default") {
+                serde.default = true;
+                skip_meta(&meta)?;
+            } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_serializing") {
+                serde.skip = true;
+            } else if meta.path.is_ident("flatten") {
+                serde.flatten = true;
+            } else if meta.path.is_ident("skip_serializing_if") {
+                serde.skip_serializing_if = true;
+                skip_meta(&meta)?;
+            } else {
+                skip_meta(&meta)?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(serde)
+}
+
+// Consumes the value or nested list of an attribute we don't care about
+fn skip_meta(meta: &ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(syn::Token![=]) {
+        meta.value()?.parse::<syn::Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(|nested| skip_meta(&nested))?;
+    }
+    Ok(())
+}
+
+// The doc comments of an item, which become its description
+fn description(attrs: &[Attribute]) -> TokenStream {
+    let lines: Vec<String> = attrs.iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match attr.meta {
+            syn::Meta::NameValue(ref nv) => match nv.value {
+                syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(ref doc), .. }) => Some(doc.value().trim().to_owned()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    let description = lines.join("\n").trim().to_owned();
+    if description.is_empty() {
+        quote!(None)
+    } else {
+        quote!(Some(#description))
+    }
+}
+
+fn split_words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for part in name.split('_').filter(|part| !part.is_empty()) {
+        let mut word = String::new();
+        for c in part.chars() {
+            if c.is_uppercase() && !word.is_empty() {
+                words.push(word);
+                word = String::new();
+            }
+            word.push(c);
+        }
+        words.push(word);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+        None => String::new(),
+    }
+}
+
+// Applies a serde `rename_all` rule to a field (snake_case) or variant (PascalCase) name
+fn rename(name: &str, rule: &str) -> syn::Result<String> {
+    let words = split_words(name);
+    let lower: Vec<String> = words.iter().map(|w| w.to_lowercase()).collect();
+    let upper: Vec<String> = words.iter().map(|w| w.to_uppercase()).collect();
+    let renamed = match rule {
+        "lowercase" => lower.concat(),
+        "UPPERCASE" => upper.concat(),
+        "PascalCase" => words.iter().map(|w| capitalize(w)).collect(),
+        "camelCase" => {
+            let pascal: String = words.iter().map(|w| capitalize(w)).collect();
+            let mut chars = pascal.chars();
+            match chars.next() {
+                Some(first) => first.to_lowercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        }
+        "snake_case" => lower.join("_"),
+        "SCREAMING_SNAKE_CASE" => upper.join("_"),
+        "kebab-case" => lower.join("-"),
+        "SCREAMING-KEBAB-CASE" => upper.join("-"),
+        _ => return Err(Error::new(Span::call_site(), format!("unknown rename_all rule `{}`", rule))),
+    };
+    Ok(renamed)
+}
+
+pub(crate) fn serialized_name(ident: &syn::Ident, serde: &SerdeAttributes, rename_all: Option<&String>) -> syn::Result<String> {
+    if let Some(ref name) = serde.rename {
+        return Ok(name.clone());
+    }
+    let name = ident.to_string();
+    let name = name.trim_start_matches("r#");
+    match rename_all {
+        Some(rule) => rename(name, rule),
+        None => Ok(name.to_owned()),
+    }
+}
+
+pub fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let container = serde_attributes(&input.attrs)?;
+    let doc = description(&input.attrs);
+
+    let body = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => {
+                let mut properties = Vec::new();
+                for field in &fields.named {
+                    let serde = serde_attributes(&field.attrs)?;
+                    if serde.skip {
+                        continue;
+                    }
+                    let ty = &field.ty;
+                    if serde.flatten {
+                        properties.push(quote! { .flatten::<#ty>() });
+                        continue;
+                    }
+                    let ident = field.ident.as_ref().expect("named fields have names");
+                    let name = serialized_name(ident, &serde, container.rename_all.as_ref())?;
+                    let field_doc = description(&field.attrs);
+                    let required = !(container.default || serde.default || serde.skip_serializing_if);
+                    properties.push(quote! { .property::<#ty>(#name, #field_doc, #required) });
+                }
+                quote! {
+                    ::qube::schema::ObjectSchema::new(#doc)
+                        #(#properties)*
+                        .build()
+                }
+            }
+            Fields::Unnamed(ref fields) if fields.unnamed.len() == 1 => {
+                let ty = &fields.unnamed[0].ty;
+                quote! { <#ty as ::qube::schema::JsonSchema>::schema() }
+            }
+            _ => return Err(Error::new_spanned(&input.ident, "JsonSchema can't be derived for unit or tuple structs")),
+        },
+        Data::Enum(ref data) => {
+            let mut variants = Vec::new();
+            for variant in &data.variants {
+                match variant.fields {
+                    Fields::Unit => {}
+                    _ => return Err(Error::new_spanned(variant, "JsonSchema can only be derived for enums of unit variants")),
+                }
+                let serde = serde_attributes(&variant.attrs)?;
+                if !serde.skip {
+                    variants.push(serialized_name(&variant.ident, &serde, container.rename_all.as_ref())?);
+                }
+            }
+            quote! { ::qube::schema::string_enum(#doc, &[#(#variants),*]) }
+        }
+        Data::Union(_) => return Err(Error::new_spanned(&input.ident, "JsonSchema can't be derived for unions")),
+    };
+
+    let mut generics = input.generics.clone();
+    for param in &mut generics.params {
+        if let GenericParam::Type(ref mut param) = *param {
+            param.bounds.push(syn::parse_quote!(::qube::schema::JsonSchema));
+        }
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let name = &input.ident;
+
+    Ok(quote! {
+        impl #impl_generics ::qube::schema::JsonSchema for #name #ty_generics #where_clause {
+            fn schema() -> ::qube::schema::Schema {
+                #body
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_are_renamed() {
+        assert_eq!(rename("ready_replicas", "camelCase").unwrap(), "readyReplicas");
+        assert_eq!(rename("ready_replicas", "PascalCase").unwrap(), "ReadyReplicas");
+        assert_eq!(rename("ready_replicas", "kebab-case").unwrap(), "ready-replicas");
+        assert_eq!(rename("ready_replicas", "SCREAMING_SNAKE_CASE").unwrap(), "READY_REPLICAS");
+        assert_eq!(rename("ready_replicas", "lowercase").unwrap(), "readyreplicas");
+    }
+
+    #[test]
+    fn variants_are_renamed() {
+        assert_eq!(rename("OnFailure", "camelCase").unwrap(), "onFailure");
+        assert_eq!(rename("OnFailure", "snake_case").unwrap(), "on_failure");
+        assert_eq!(rename("OnFailure", "SCREAMING-KEBAB-CASE").unwrap(), "ON-FAILURE");
+        assert_eq!(rename("OnFailure", "UPPERCASE").unwrap(), "ONFAILURE");
+    }
+
+    #[test]
+    fn unknown_rules_are_rejected() {
+        assert!(rename("ready_replicas", "Title Case").is_err());
+    }
+
+    #[test]
+    fn explicit_renames_win_over_rename_all() {
+        let field: syn::Field = syn::parse_quote! {
+            #[serde(rename = "ttl")]
+            time_to_live: u32
+        };
+        let serde = serde_attributes(&field.attrs).unwrap();
+        let ident = field.ident.as_ref().unwrap();
+        assert_eq!(serialized_name(ident, &serde, Some(&"camelCase".to_owned())).unwrap(), "ttl");
+    }
+
+    #[test]
+    fn raw_identifiers_lose_their_prefix() {
+        let ident: syn::Ident = syn::parse_quote!(r#type);
+        assert_eq!(serialized_name(&ident, &SerdeAttributes::default(), None).unwrap(), "type");
+    }
+}
@@ -0,0 +1,82 @@
+//! Derive macros for the custom resources of `qube`
+//!
+//! ## `#[derive(CustomResource)]`
+//!
+//! Generates a custom resource kind from its spec struct. The derive goes on the spec,
+//! and the `#[resource(...)]` attribute describes the kind:
+//!
+//! - `group`, `version` and `kind` (required), e.g. `group = "example.com"`
+//! - `plural`, the name of the kind in URLs, defaults to the lowercase kind with an `s`
+//! - `namespaced`, for kinds living in a namespace, otherwise they are cluster-scoped
+//! - `status = "FooStatus"`, the type of the `status` field, which implements `HasStatus`
+//! - `scale` or `scale = "field"`, adds the `/scale` subresource and implements `Scalable`
+//!   from an `Option<i32>` field of the status counting ready replicas, `ready_replicas` by default.
+//!   The spec must have a `replicas: Option<i32>` field, which is scaled under its serialized name,
+//!   and the status must serialize the observed number of replicas as `replicas`
+//!
+//! This generates a struct named after the kind, with `types`, `metadata`, `spec` and
+//! `status` fields, a `<Kind>List` type and the `Resource`, `ListableResource` and
+//! `CustomResourceExt` impls. The generated types derive `Serialize` and `Deserialize`,
+//! so those derives must be in scope, and the spec (and status) must implement `Clone`,
+//! `Debug` and `JsonSchema`.
+//!
+//! ## `#[derive(JsonSchema)]`
+//!
+//! Implements `qube::schema::JsonSchema` for structs and enums of unit variants,
+//! following their serde attributes and turning doc comments into descriptions.
+//!
+//! ## Examples
+//!
+//! ```ignore
+//! #[macro_use] extern crate serde_derive;
+//! #[macro_use] extern crate qube_derive;
+//! extern crate qube;
+//!
+//! /// Backs up a database on a schedule
+//! #[derive(CustomResource, JsonSchema, Serialize, Deserialize, Clone, Debug)]
+//! #[resource(group = "example.com", version = "v1", kind = "Backup", namespaced,
+//!            status = "BackupStatus")]
+//! pub struct BackupSpec {
+//!     /// When to run the backup, in cron syntax
+//!     pub schedule: String,
+//! }
+//!
+//! #[derive(JsonSchema, Serialize, Deserialize, Clone, Debug)]
+//! pub struct BackupStatus {
+//!     pub last_run: Option<String>,
+//! }
+//!
+//! let kube = qube::Kubernetes::load_conf("admin.conf")?;
+//! kube.ensure_crd::<Backup>(Duration::from_secs(30))?;
+//! let backup = Backup::new("nightly", BackupSpec { schedule: "0 3 * * *".to_owned() });
+//! kube.client::<Backup>().create(&backup)?;
+//! ```
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use] extern crate quote;
+extern crate syn;
+
+mod custom_resource;
+mod json_schema;
+
+use proc_macro::TokenStream;
+use syn::DeriveInput;
+
+#[proc_macro_derive(CustomResource, attributes(resource))]
+pub fn derive_custom_resource(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    match custom_resource::expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+#[proc_macro_derive(JsonSchema)]
+pub fn derive_json_schema(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    match json_schema::expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
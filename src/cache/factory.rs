@@ -0,0 +1,127 @@
+use super::informer::*;
+use clients::Kubernetes;
+use resources::*;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::thread;
+
+// Informers are shared between every consumer asking for the same kind,
+// namespace and selectors
+type InformerKey = (TypeId, Option<String>, Vec<(String, String)>);
+
+// Type-erased view of an `Informer<R>`, so the factory can drive all of its informers
+trait SharedInformer: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn run(&self);
+    fn stop(&self);
+    fn has_synced(&self) -> bool;
+}
+
+impl<R> SharedInformer for Informer<R>
+where R: ListableResource + Send + Sync + 'static
+{
+    fn as_any(&self) -> &dyn Any { self }
+    fn run(&self) { Informer::run(self) }
+    fn stop(&self) { Informer::stop(self) }
+    fn has_synced(&self) -> bool { Informer::has_synced(self) }
+}
+
+/// Hands out informers that share a single watch per kind, namespace and selector
+///
+/// A factory is bound to one `Kubernetes` client; consumers asking for the same
+/// informer get clones sharing one cache and one connection.
+///
+/// ## Examples
+///
+/// ```no_run
+/// # use qube::prelude::*;
+/// # use qube::cache::{InformerFactory, HandlerFuncs};
+/// # use qube::resources::{ListQuery, Pod};
+/// # use std::time::Duration;
+/// let kube = Kubernetes::load_conf("admin.conf")?;
+/// let factory = InformerFactory::new(&kube, Some(Duration::from_secs(300)));
+///
+/// let pods = factory.informer::<Pod>(Some("default"), &ListQuery::default());
+/// pods.add_event_handler(HandlerFuncs::new().on_delete(|pod: &Pod| {
+///     println!("Deleted pod {:?}", pod.metadata.name)
+/// }));
+///
+/// factory.start();
+/// factory.wait_for_cache_sync(Duration::from_secs(30));
+/// # Ok::<(), qube::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct InformerFactory {
+    kube: Kubernetes,
+    resync_period: Option<Duration>,
+    informers: Arc<Mutex<HashMap<InformerKey, Box<dyn SharedInformer>>>>,
+}
+
+impl InformerFactory {
+    /// Creates a factory whose informers resync every `resync_period`
+    pub fn new(kube: &Kubernetes, resync_period: Option<Duration>) -> InformerFactory {
+        InformerFactory {
+            kube: kube.clone(),
+            resync_period,
+            informers: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Gets the shared informer for a kind in `namespace` (or the client's namespace,
+    /// which may be all namespaces, see `Kubernetes::all_namespaces`),
+    /// filtered by the label/field selectors of `query`
+    ///
+    /// Informers created after `start` are not running until `start` is called again.
+    pub fn informer<R>(&self, namespace: Option<&str>, query: &ListQuery) -> Informer<R>
+    where R: ListableResource + Send + Sync + 'static
+    {
+        let kube = match namespace {
+            Some(ns) => self.kube.namespace(ns),
+            None => self.kube.clone(),
+        };
+        let pairs = query.as_query_pairs()
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v))
+            .collect();
+        let key = (TypeId::of::<R>(), kube.list_ns::<R>().map(str::to_owned), pairs);
+
+        let mut informers = self.informers.lock().unwrap();
+        let informer = informers.entry(key).or_insert_with(|| {
+            Box::new(Informer::<R>::new(&kube, query.clone(), self.resync_period))
+        });
+        informer.as_any()
+            .downcast_ref::<Informer<R>>()
+            .expect("kubeclient bug: informer registered under the wrong type")
+            .clone()
+    }
+
+    /// Starts every informer created so far that is not already running
+    pub fn start(&self) {
+        for informer in self.informers.lock().unwrap().values() {
+            informer.run();
+        }
+    }
+
+    /// Stops every informer created so far
+    pub fn shutdown(&self) {
+        for informer in self.informers.lock().unwrap().values() {
+            informer.stop();
+        }
+    }
+
+    /// Blocks until every informer has synced, or `timeout` elapsed
+    ///
+    /// Returns whether all caches are synced.
+    pub fn wait_for_cache_sync(&self, timeout: Duration) -> bool {
+        let start = Instant::now();
+        loop {
+            let synced = self.informers.lock().unwrap().values().all(|i| i.has_synced());
+            if synced || start.elapsed() >= timeout {
+                return synced;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
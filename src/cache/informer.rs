@@ -0,0 +1,491 @@
+use super::store::*;
+use clients::Kubernetes;
+use resources::*;
+use errors::*;
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+// The blocking HTTP client gives up on reads after 30 seconds, so watches are
+// closed by the server a bit earlier than that and simply re-established.
+const WATCH_TIMEOUT_SECONDS: u32 = 25;
+const RETRY_PERIOD_MILLIS: u64 = 1000;
+const TICK_MILLIS: u64 = 100;
+
+/// Callbacks notified by an `Informer` about changes to the resources it watches
+///
+/// All methods default to doing nothing, so implementors only need to
+/// override the notifications they care about.
+pub trait ResourceEventHandler<R>: Send + Sync {
+    /// Called when a resource appears in the cache
+    fn on_add(&self, _resource: &R) {}
+
+    /// Called when a cached resource changes, and for every cached resource on resync
+    fn on_update(&self, _old: &R, _new: &R) {}
+
+    /// Called when a resource disappears from the cache
+    fn on_delete(&self, _resource: &R) {}
+}
+
+/// A `ResourceEventHandler` built from closures
+///
+/// ## Examples
+///
+/// ```no_run
+/// # use qube::cache::HandlerFuncs;
+/// # use qube::resources::Pod;
+/// let handler = HandlerFuncs::<Pod>::new()
+///     .on_add(|pod| println!("Added {:?}", pod.metadata.name))
+///     .on_delete(|pod| println!("Deleted {:?}", pod.metadata.name));
+/// # Ok::<(), qube::Error>(())
+/// ```
+pub struct HandlerFuncs<R> {
+    add: Option<ResourceFn<R>>,
+    update: Option<UpdateFn<R>>,
+    delete: Option<ResourceFn<R>>,
+}
+
+type ResourceFn<R> = Box<dyn Fn(&R) + Send + Sync>;
+type UpdateFn<R> = Box<dyn Fn(&R, &R) + Send + Sync>;
+
+impl<R> HandlerFuncs<R> {
+    pub fn new() -> HandlerFuncs<R> {
+        HandlerFuncs { add: None, update: None, delete: None }
+    }
+
+    pub fn on_add<F>(mut self, f: F) -> Self
+    where F: Fn(&R) + Send + Sync + 'static
+    {
+        self.add = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_update<F>(mut self, f: F) -> Self
+    where F: Fn(&R, &R) + Send + Sync + 'static
+    {
+        self.update = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_delete<F>(mut self, f: F) -> Self
+    where F: Fn(&R) + Send + Sync + 'static
+    {
+        self.delete = Some(Box::new(f));
+        self
+    }
+}
+
+impl<R> Default for HandlerFuncs<R> {
+    fn default() -> Self {
+        HandlerFuncs::new()
+    }
+}
+
+impl<R> ResourceEventHandler<R> for HandlerFuncs<R> {
+    fn on_add(&self, resource: &R) {
+        if let Some(ref f) = self.add {
+            f(resource)
+        }
+    }
+
+    fn on_update(&self, old: &R, new: &R) {
+        if let Some(ref f) = self.update {
+            f(old, new)
+        }
+    }
+
+    fn on_delete(&self, resource: &R) {
+        if let Some(ref f) = self.delete {
+            f(resource)
+        }
+    }
+}
+
+/// Keeps a `Store` in sync with the cluster and notifies handlers about changes
+///
+/// An informer lists the resources once, then watches for changes starting at the
+/// list's `resourceVersion`. Dropped watches are resumed, and expired ones trigger a relist.
+/// Informers are cheap to clone; all clones share the same cache, handlers and watch.
+///
+/// ## Examples
+///
+/// ```no_run
+/// # use qube::prelude::*;
+/// # use qube::cache::{Informer, HandlerFuncs};
+/// # use qube::resources::{ListQuery, Pod};
+/// # use std::time::Duration;
+/// let kube = Kubernetes::load_conf("admin.conf")?;
+/// let informer = Informer::<Pod>::new(&kube, ListQuery::default(), Some(Duration::from_secs(300)));
+/// informer.add_event_handler(HandlerFuncs::new().on_add(|pod: &Pod| {
+///     println!("Found pod {:?}", pod.metadata.name)
+/// }));
+/// informer.run();
+/// informer.wait_for_sync(Duration::from_secs(30));
+/// # Ok::<(), qube::Error>(())
+/// ```
+pub struct Informer<R> {
+    inner: Arc<InformerInner<R>>,
+}
+
+type SharedHandler<R> = Arc<dyn ResourceEventHandler<R>>;
+
+struct InformerInner<R> {
+    kube: Kubernetes,
+    query: ListQuery,
+    resync_period: Option<Duration>,
+    store: Store<R>,
+    // Replaced rather than mutated, so that handlers run on a snapshot without holding the lock
+    handlers: Mutex<Arc<Vec<SharedHandler<R>>>>,
+    // Held while the store changes and handlers are notified, so that a handler being added
+    // sees every resource exactly once. `dispatcher` is the thread holding it, which may
+    // dispatch again, e.g. when a handler adds another handler.
+    dispatch: Mutex<()>,
+    dispatcher: Mutex<Option<ThreadId>>,
+    started: AtomicBool,
+    stopped: AtomicBool,
+    synced: AtomicBool,
+}
+
+impl<R> Clone for Informer<R> {
+    fn clone(&self) -> Self {
+        Informer { inner: self.inner.clone() }
+    }
+}
+
+impl<R> Informer<R>
+where R: ListableResource + Send + Sync + 'static
+{
+    /// Creates an informer for the resources selected by `query`
+    ///
+    /// The informer watches the namespace of `kube` (or the kind's default namespace).
+    /// When `resync_period` is set, every cached resource is periodically
+    /// redelivered to the handlers through `on_update`.
+    pub fn new(kube: &Kubernetes, query: ListQuery, resync_period: Option<Duration>) -> Informer<R> {
+        Informer {
+            inner: Arc::new(InformerInner {
+                kube: kube.clone(),
+                query,
+                resync_period,
+                store: Store::new(),
+                handlers: Mutex::new(Arc::new(Vec::new())),
+                dispatch: Mutex::new(()),
+                dispatcher: Mutex::new(None),
+                started: AtomicBool::new(false),
+                stopped: AtomicBool::new(false),
+                synced: AtomicBool::new(false),
+            })
+        }
+    }
+
+    /// The local cache maintained by this informer
+    pub fn store(&self) -> Store<R> {
+        self.inner.store.clone()
+    }
+
+    /// Registers a handler
+    ///
+    /// Handlers added after the informer started are first sent an `on_add` for every
+    /// resource already in the cache, then notified about the changes that follow,
+    /// so each resource is added exactly once. Handlers may add other handlers.
+    pub fn add_event_handler<H>(&self, handler: H)
+    where H: ResourceEventHandler<R> + 'static
+    {
+        let handler = Arc::new(handler);
+        self.inner.dispatch(|| {
+            for resource in self.inner.store.list() {
+                handler.on_add(&resource);
+            }
+            let mut handlers = self.inner.handlers.lock().unwrap();
+            let mut updated = Vec::clone(&handlers);
+            updated.push(handler.clone());
+            *handlers = Arc::new(updated);
+        });
+    }
+
+    /// Starts listing and watching in background threads
+    ///
+    /// Calling `run` on an informer that is already running does nothing.
+    pub fn run(&self) {
+        if self.inner.started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let inner = self.inner.clone();
+        thread::spawn(move || {
+            while !inner.is_stopped() {
+                if inner.list_and_watch().is_err() {
+                    inner.sleep(Duration::from_millis(RETRY_PERIOD_MILLIS));
+                }
+            }
+        });
+
+        if let Some(period) = self.inner.resync_period {
+            let inner = self.inner.clone();
+            thread::spawn(move || {
+                while !inner.is_stopped() {
+                    inner.sleep(period);
+                    if !inner.is_stopped() && inner.synced.load(Ordering::SeqCst) {
+                        inner.resync();
+                    }
+                }
+            });
+        }
+    }
+
+    /// Stops the background threads
+    ///
+    /// A watch in flight is abandoned once its current request ends.
+    pub fn stop(&self) {
+        self.inner.stopped.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.inner.started.load(Ordering::SeqCst) && !self.inner.is_stopped()
+    }
+
+    /// Indicates whether the initial list has been loaded into the cache
+    pub fn has_synced(&self) -> bool {
+        self.inner.synced.load(Ordering::SeqCst)
+    }
+
+    /// Blocks until the initial list has been loaded, or `timeout` elapsed
+    ///
+    /// Returns whether the cache is synced.
+    pub fn wait_for_sync(&self, timeout: Duration) -> bool {
+        let start = Instant::now();
+        while !self.has_synced() && start.elapsed() < timeout && !self.inner.is_stopped() {
+            thread::sleep(Duration::from_millis(TICK_MILLIS));
+        }
+        self.has_synced()
+    }
+}
+
+impl<R> InformerInner<R>
+where R: ListableResource + Send + Sync + 'static
+{
+    fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+
+    // Sleeps in short ticks so that `stop` is noticed promptly
+    fn sleep(&self, duration: Duration) {
+        let start = Instant::now();
+        while !self.is_stopped() && start.elapsed() < duration {
+            thread::sleep(Duration::from_millis(TICK_MILLIS));
+        }
+    }
+
+    fn list_and_watch(&self) -> Result<()> {
+        let (items, mut version) = self.kube.list_with_version::<R>(Some(&self.query))?;
+        self.replace(items);
+        self.synced.store(true, Ordering::SeqCst);
+
+        while !self.is_stopped() {
+            let mut query = self.query.timeout_seconds(WATCH_TIMEOUT_SECONDS);
+            if let Some(ref version) = version {
+                query = query.resource_version(version.as_str());
+            }
+
+            for event in self.kube.watch::<R>(Some(&query))? {
+                if self.is_stopped() {
+                    return Ok(());
+                }
+                let resource = match event {
+                    Ok(WatchEvent::Added(resource)) | Ok(WatchEvent::Modified(resource)) => {
+                        if resource.metadata().resource_version.is_some() {
+                            version = resource.metadata().resource_version.clone();
+                        }
+                        self.upsert(resource);
+                        continue;
+                    }
+                    Ok(WatchEvent::Deleted(resource)) => resource,
+                    // 410 Gone: our resourceVersion is too old to resume from, so relist
+                    Ok(WatchEvent::Error(ref status)) if ApiError::from(status).is_gone() => return Ok(()),
+                    Ok(WatchEvent::Error(ref status)) => bail!(ApiError::from(status)),
+                    // The connection dropped; resume watching from the last seen version
+                    Err(_) => break,
+                };
+                if resource.metadata().resource_version.is_some() {
+                    version = resource.metadata().resource_version.clone();
+                }
+                self.delete(&object_key(&resource));
+            }
+        }
+        Ok(())
+    }
+
+    fn handlers(&self) -> Arc<Vec<SharedHandler<R>>> {
+        self.handlers.lock().unwrap().clone()
+    }
+
+    fn dispatch<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        let current = thread::current().id();
+        if *self.dispatcher.lock().unwrap() == Some(current) {
+            return f();
+        }
+        let _guard = self.dispatch.lock().unwrap();
+        *self.dispatcher.lock().unwrap() = Some(current);
+        let result = f();
+        *self.dispatcher.lock().unwrap() = None;
+        result
+    }
+
+    fn upsert(&self, resource: R) {
+        self.dispatch(|| {
+            let handlers = self.handlers();
+            match self.store.insert(resource) {
+                (new, Some(old)) => for handler in handlers.iter() {
+                    handler.on_update(&old, &new)
+                },
+                (new, None) => for handler in handlers.iter() {
+                    handler.on_add(&new)
+                },
+            }
+        })
+    }
+
+    fn delete(&self, key: &str) {
+        self.dispatch(|| {
+            let handlers = self.handlers();
+            if let Some(old) = self.store.remove(key) {
+                for handler in handlers.iter() {
+                    handler.on_delete(&old)
+                }
+            }
+        })
+    }
+
+    // Reconciles the cache with a fresh list, notifying handlers about every difference
+    fn replace(&self, items: Vec<R>) {
+        let keys: BTreeSet<String> = items.iter().map(object_key).collect();
+        for key in self.store.keys() {
+            if !keys.contains(&key) {
+                self.delete(&key);
+            }
+        }
+        for resource in items {
+            self.upsert(resource);
+        }
+    }
+
+    fn resync(&self) {
+        self.dispatch(|| {
+            let handlers = self.handlers();
+            for resource in self.store.list() {
+                for handler in handlers.iter() {
+                    handler.on_update(&resource, &resource)
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake_server::FakeServer;
+
+    fn lease(name: &str, version: &str) -> Lease {
+        let mut lease = Lease::new(name);
+        lease.metadata.namespace = Some("default".to_owned());
+        lease.metadata.resource_version = Some(version.to_owned());
+        lease
+    }
+
+    fn describe(lease: &Lease) -> String {
+        let metadata = &lease.metadata;
+        format!("{}@{}", metadata.name.as_deref().unwrap(), metadata.resource_version.as_deref().unwrap())
+    }
+
+    // Records every notification as e.g. `add a@1` or `update a@1 a@2`
+    fn recorder(events: &Arc<Mutex<Vec<String>>>) -> HandlerFuncs<Lease> {
+        let (added, updated, deleted) = (events.clone(), events.clone(), events.clone());
+        HandlerFuncs::new()
+            .on_add(move |new| added.lock().unwrap().push(format!("add {}", describe(new))))
+            .on_update(move |old, new| updated.lock().unwrap().push(format!("update {} {}", describe(old), describe(new))))
+            .on_delete(move |old| deleted.lock().unwrap().push(format!("delete {}", describe(old))))
+    }
+
+    fn informer() -> Informer<Lease> {
+        Informer::new(&FakeServer::start().kube(), ListQuery::default(), None)
+    }
+
+    fn take(events: &Arc<Mutex<Vec<String>>>) -> Vec<String> {
+        events.lock().unwrap().drain(..).collect()
+    }
+
+    #[test]
+    fn handlers_are_notified_of_changes() {
+        let informer = informer();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        informer.add_event_handler(recorder(&events));
+
+        informer.inner.upsert(lease("a", "1"));
+        informer.inner.upsert(lease("a", "2"));
+        informer.inner.delete("default/a");
+        informer.inner.delete("default/a");
+        assert_eq!(take(&events), vec!["add a@1", "update a@1 a@2", "delete a@2"]);
+        assert!(informer.store().is_empty());
+    }
+
+    #[test]
+    fn relisting_replaces_the_cache() {
+        let informer = informer();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        informer.add_event_handler(recorder(&events));
+        informer.inner.replace(vec![lease("a", "1"), lease("b", "1")]);
+        take(&events);
+
+        informer.inner.replace(vec![lease("b", "2"), lease("c", "3")]);
+        assert_eq!(take(&events), vec!["delete a@1", "update b@1 b@2", "add c@3"]);
+        assert_eq!(informer.store().keys(), vec!["default/b", "default/c"]);
+    }
+
+    #[test]
+    fn resync_redelivers_the_cache() {
+        let informer = informer();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        informer.inner.replace(vec![lease("a", "1"), lease("b", "1")]);
+        informer.add_event_handler(recorder(&events));
+        assert_eq!(take(&events), vec!["add a@1", "add b@1"]);
+
+        informer.inner.resync();
+        assert_eq!(take(&events), vec!["update a@1 a@1", "update b@1 b@1"]);
+    }
+
+    #[test]
+    fn handlers_added_by_handlers_see_each_resource_once() {
+        let informer = informer();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let (adder, recorded) = (informer.clone(), events.clone());
+        informer.add_event_handler(HandlerFuncs::new().on_add(move |_: &Lease| {
+            adder.add_event_handler(recorder(&recorded));
+        }));
+
+        informer.inner.upsert(lease("a", "1"));
+        assert_eq!(take(&events), vec!["add a@1"]);
+        informer.inner.upsert(lease("a", "2"));
+        assert_eq!(take(&events), vec!["update a@1 a@2"]);
+    }
+
+    #[test]
+    fn handlers_added_concurrently_see_each_resource_once() {
+        let informer = informer();
+        let writer = {
+            let informer = informer.clone();
+            thread::spawn(move || for i in 0..200 {
+                informer.inner.upsert(lease(&format!("lease-{:03}", i), "1"));
+            })
+        };
+        let events = Arc::new(Mutex::new(Vec::new()));
+        informer.add_event_handler(recorder(&events));
+        writer.join().unwrap();
+
+        let mut added = take(&events);
+        added.sort();
+        let expected: Vec<String> = (0..200).map(|i| format!("add lease-{:03}@1", i)).collect();
+        assert_eq!(added, expected);
+    }
+}
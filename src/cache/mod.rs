@@ -0,0 +1,14 @@
+//! Local caches of kubernetes resources kept up to date by watches
+//!
+//! An [`Informer`](struct.Informer.html) lists and watches one kind of resource,
+//! mirrors it into a [`Store`](struct.Store.html), and notifies registered
+//! [`ResourceEventHandler`](trait.ResourceEventHandler.html)s about every change.
+//! An [`InformerFactory`](struct.InformerFactory.html) shares those informers between consumers.
+
+mod factory;
+mod informer;
+mod store;
+
+pub use self::factory::*;
+pub use self::informer::*;
+pub use self::store::*;
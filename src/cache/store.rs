@@ -0,0 +1,129 @@
+use resources::Resource;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+/// Builds the cache key of a resource, i.e. `<namespace>/<name>` or just `<name>`
+/// for cluster-scoped resources.
+pub fn object_key<R: Resource>(resource: &R) -> String {
+    let metadata = resource.metadata();
+    let name = metadata.name.as_deref().unwrap_or("");
+    key(metadata.namespace.as_deref(), name)
+}
+
+pub(crate) fn key(namespace: Option<&str>, name: &str) -> String {
+    match namespace {
+        Some(ns) => format!("{}/{}", ns, name),
+        None => name.to_owned(),
+    }
+}
+
+/// A thread-safe local cache of kubernetes resources, indexed by `object_key`
+///
+/// Stores are cheap to clone; all clones share the same underlying cache.
+pub struct Store<R> {
+    items: Arc<RwLock<BTreeMap<String, Arc<R>>>>,
+}
+
+impl<R> Clone for Store<R> {
+    fn clone(&self) -> Self {
+        Store { items: self.items.clone() }
+    }
+}
+
+impl<R: Resource> Store<R> {
+    pub fn new() -> Store<R> {
+        Store { items: Arc::new(RwLock::new(BTreeMap::new())) }
+    }
+
+    /// Gets a cached resource by its `object_key`
+    pub fn get(&self, key: &str) -> Option<Arc<R>> {
+        self.items.read().unwrap().get(key).cloned()
+    }
+
+    /// Gets a cached resource by namespace and name
+    pub fn get_by_name(&self, namespace: Option<&str>, name: &str) -> Option<Arc<R>> {
+        self.get(&key(namespace, name))
+    }
+
+    /// Lists every cached resource, ordered by key
+    pub fn list(&self) -> Vec<Arc<R>> {
+        self.items.read().unwrap().values().cloned().collect()
+    }
+
+    /// Lists the keys of every cached resource
+    pub fn keys(&self) -> Vec<String> {
+        self.items.read().unwrap().keys().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.read().unwrap().is_empty()
+    }
+
+    /// Inserts or replaces a resource, returning the new entry and the replaced one (if any)
+    pub(crate) fn insert(&self, resource: R) -> (Arc<R>, Option<Arc<R>>) {
+        let resource = Arc::new(resource);
+        let old = self.items.write().unwrap().insert(object_key(&*resource), resource.clone());
+        (resource, old)
+    }
+
+    pub(crate) fn remove(&self, key: &str) -> Option<Arc<R>> {
+        self.items.write().unwrap().remove(key)
+    }
+}
+
+impl<R: Resource> Default for Store<R> {
+    fn default() -> Self {
+        Store::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use resources::ConfigMap;
+
+    fn config_map(namespace: Option<&str>, name: &str) -> ConfigMap {
+        let mut cfg_map = ConfigMap::new(name);
+        cfg_map.metadata_mut().namespace = namespace.map(str::to_owned);
+        cfg_map
+    }
+
+    #[test]
+    fn keys_include_the_namespace() {
+        assert_eq!(object_key(&config_map(Some("kube-system"), "dns")), "kube-system/dns");
+        assert_eq!(object_key(&config_map(None, "dns")), "dns");
+    }
+
+    #[test]
+    fn insert_returns_the_replaced_resource() {
+        let store = Store::new();
+        let (_, old) = store.insert(config_map(Some("default"), "app"));
+        assert!(old.is_none());
+
+        let mut updated = config_map(Some("default"), "app");
+        updated.metadata_mut().resource_version = Some("2".to_owned());
+        let (new, old) = store.insert(updated);
+        assert_eq!(old.unwrap().metadata().resource_version, None);
+        assert_eq!(new.metadata().resource_version.as_deref(), Some("2"));
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn lookups_by_key_and_name_agree() {
+        let store = Store::new();
+        store.insert(config_map(Some("default"), "b"));
+        store.insert(config_map(Some("default"), "a"));
+        assert!(store.get("default/a").is_some());
+        assert!(store.get_by_name(Some("default"), "b").is_some());
+        assert!(store.get_by_name(None, "b").is_none());
+        assert_eq!(store.keys(), vec!["default/a", "default/b"]);
+
+        assert!(store.remove("default/a").is_some());
+        assert!(store.remove("default/a").is_none());
+        assert_eq!(store.len(), 1);
+    }
+}
@@ -0,0 +1,187 @@
+use errors::*;
+use resources::{ListableResource, Status};
+use serde::de::DeserializeOwned;
+use serde_json::{self, Value};
+
+/// What happens to the dependents (e.g. the pods of a replica set) of a deleted resource
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PropagationPolicy {
+    /// Dependents are left alone, and their owner references removed
+    Orphan,
+
+    /// The resource is deleted immediately, then the garbage collector deletes its dependents
+    Background,
+
+    /// The resource is only deleted once the garbage collector deleted all of its
+    /// "blocking" dependents
+    Foreground,
+}
+
+/// Conditions that must hold for a deletion to proceed
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Preconditions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uid: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_version: Option<String>,
+}
+
+/// Options of a delete request, sent as `DeleteOptions`
+///
+/// ## Examples
+///
+/// ```no_run
+/// # use qube::prelude::*;
+/// # use qube::clients::{DeleteParams, PropagationPolicy};
+/// let kube = Kubernetes::load_conf("admin.conf")?;
+/// let params = DeleteParams::default()
+///     .propagation_policy(PropagationPolicy::Foreground)
+///     .grace_period_seconds(0);
+/// kube.deployments().delete_with("web-server", &params)?;
+/// # Ok::<(), qube::Error>(())
+/// ```
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    propagation_policy: Option<PropagationPolicy>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    grace_period_seconds: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    preconditions: Option<Preconditions>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    dry_run: Vec<&'static str>,
+}
+
+impl DeleteParams {
+    pub fn propagation_policy(mut self, policy: PropagationPolicy) -> Self {
+        self.propagation_policy = Some(policy);
+        self
+    }
+
+    /// How long the resource may take to terminate gracefully, 0 deletes it immediately
+    pub fn grace_period_seconds(mut self, seconds: u32) -> Self {
+        self.grace_period_seconds = Some(seconds);
+        self
+    }
+
+    /// Only deletes the resource if its UID matches, i.e. not a recreated one with the same name
+    pub fn uid_precondition(mut self, uid: &str) -> Self {
+        self.preconditions.get_or_insert_with(Default::default).uid = Some(uid.to_owned());
+        self
+    }
+
+    /// Only deletes the resource if it was not modified since `resource_version`
+    pub fn resource_version_precondition(mut self, resource_version: &str) -> Self {
+        self.preconditions.get_or_insert_with(Default::default).resource_version = Some(resource_version.to_owned());
+        self
+    }
+
+    /// Validates the deletion without persisting it
+    pub fn dry_run(mut self) -> Self {
+        self.dry_run = vec!["All"];
+        self
+    }
+
+    pub(crate) fn to_body(&self) -> Result<Value> {
+        let mut body = serde_json::to_value(self)?;
+        if let Value::Object(ref mut fields) = body {
+            fields.insert("kind".to_owned(), Value::String("DeleteOptions".to_owned()));
+            fields.insert("apiVersion".to_owned(), Value::String("v1".to_owned()));
+        }
+        Ok(body)
+    }
+}
+
+/// The outcome of a delete request
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug)]
+pub enum DeleteResponse<T> {
+    /// The deletion is complete
+    Deleted(Status),
+
+    /// The deletion is in progress, e.g. pending finalizers or a graceful termination;
+    /// this is the state of the resource(s) once marked for deletion
+    Pending(T),
+}
+
+impl<T: DeserializeOwned> DeleteResponse<T> {
+    // The server responds either with a `Status`, or with the resource(s) being deleted
+    pub(crate) fn from_value(value: Value) -> Result<DeleteResponse<T>> {
+        if value.get("kind").and_then(Value::as_str) == Some("Status") {
+            Ok(DeleteResponse::Deleted(serde_json::from_value(value)?))
+        } else {
+            Ok(DeleteResponse::Pending(serde_json::from_value(value)?))
+        }
+    }
+}
+
+impl<R: ListableResource> DeleteResponse<Vec<R>> {
+    pub(crate) fn from_list_value(value: Value) -> Result<DeleteResponse<Vec<R>>> {
+        match DeleteResponse::<R::ListResponse>::from_value(value)? {
+            DeleteResponse::Deleted(status) => Ok(DeleteResponse::Deleted(status)),
+            DeleteResponse::Pending(list) => Ok(DeleteResponse::Pending(R::typed_list_items(list))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use resources::Pod;
+    use serde_json::json;
+
+    #[test]
+    fn params_are_sent_as_delete_options() {
+        let params = DeleteParams::default()
+            .propagation_policy(PropagationPolicy::Foreground)
+            .grace_period_seconds(0)
+            .uid_precondition("5e2b")
+            .resource_version_precondition("42")
+            .dry_run();
+        assert_eq!(params.to_body().unwrap(), json!({
+            "kind": "DeleteOptions",
+            "apiVersion": "v1",
+            "propagationPolicy": "Foreground",
+            "gracePeriodSeconds": 0,
+            "preconditions": { "uid": "5e2b", "resourceVersion": "42" },
+            "dryRun": ["All"],
+        }));
+        assert_eq!(DeleteParams::default().to_body().unwrap(), json!({ "kind": "DeleteOptions", "apiVersion": "v1" }));
+    }
+
+    #[test]
+    fn responses_are_told_apart_by_kind() {
+        let status = json!({ "kind": "Status", "apiVersion": "v1", "status": "Success", "details": { "name": "web" } });
+        match DeleteResponse::<Pod>::from_value(status).unwrap() {
+            DeleteResponse::Deleted(status) => assert_eq!(status.status, "Success"),
+            other => panic!("expected Deleted, got {:?}", other),
+        }
+
+        let pod = json!({ "kind": "Pod", "apiVersion": "v1", "metadata": { "name": "web", "deletionGracePeriodSeconds": 30 }, "spec": { "containers": [] } });
+        match DeleteResponse::<Pod>::from_value(pod).unwrap() {
+            DeleteResponse::Pending(pod) => assert_eq!(pod.metadata.deletion_grace_period_seconds, Some(30)),
+            other => panic!("expected Pending, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn collection_responses_list_their_items() {
+        let list = json!({ "kind": "PodList", "apiVersion": "v1", "items": [
+            { "metadata": { "name": "a" }, "spec": { "containers": [] } },
+            { "metadata": { "name": "b" }, "spec": { "containers": [] } },
+        ] });
+        match DeleteResponse::<Vec<Pod>>::from_list_value(list).unwrap() {
+            DeleteResponse::Pending(pods) => {
+                let names: Vec<_> = pods.iter().map(|pod| pod.metadata.name.clone().unwrap()).collect();
+                assert_eq!(names, vec!["a", "b"]);
+            }
+            other => panic!("expected Pending, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use super::{DeleteParams, DeleteResponse, KindRoute, Kubernetes, Patch, ResourceRoute, WatchEvents};
+use errors::*;
+use resources::*;
+
+/// A client for resources of a kind only known at runtime, e.g. a custom resource
+///
+/// ## Examples
+///
+/// ```no_run
+/// # use qube::prelude::*;
+/// # use qube::resources::ApiResource;
+/// let kube = Kubernetes::load_conf("admin.conf")?;
+/// let certificates = ApiResource::new("cert-manager.io", "v1", "Certificate", "certificates", true);
+/// for certificate in kube.dynamic(certificates).list(None)? {
+///     println!("{:?}: {}", certificate.metadata.name, certificate.data["spec"]["secretName"]);
+/// }
+/// # Ok::<(), qube::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct DynamicClient {
+    kube: Kubernetes,
+    resource: ApiResource,
+    api: String,
+}
+
+impl DynamicClient {
+    pub(super) fn new(kube: &Kubernetes, resource: ApiResource) -> DynamicClient {
+        DynamicClient { kube: kube.clone(), api: resource.api(), resource }
+    }
+
+    /// Get a dynamic client that uses a specific namespace
+    pub fn namespace(&self, namespace: &str) -> DynamicClient {
+        DynamicClient { kube: self.kube.namespace(namespace), ..self.clone() }
+    }
+
+    /// Get a dynamic client that lists and watches resources across all namespaces
+    pub fn all_namespaces(&self) -> DynamicClient {
+        DynamicClient { kube: self.kube.all_namespaces(), ..self.clone() }
+    }
+
+    /// The kind this client manages
+    pub fn api_resource(&self) -> &ApiResource {
+        &self.resource
+    }
+
+    /// Indicates whether or not the named resource exists in the Kubernetes cluster
+    pub fn exists(&self, name: &str) -> Result<bool> {
+        self.kube.low_level.exists(&self.resource_route(name))
+    }
+
+    /// Gets the named resource
+    pub fn get(&self, name: &str) -> Result<DynamicObject> {
+        let object: DynamicObject = self.kube.low_level.get(&self.resource_route(name))?;
+        Ok(object.with_types(&self.resource))
+    }
+
+    /// Lists resources of this kind
+    pub fn list(&self, query: Option<&ListQuery>) -> Result<Vec<DynamicObject>> {
+        let mut route = self.kind_route(self.list_ns());
+        if let Some(query) = query {
+            route.query(query.as_query_pairs());
+        }
+        let response: DynamicObjectList = self.kube.low_level.list(&route)?;
+        Ok(response.items.into_iter().map(|o| o.with_types(&self.resource)).collect())
+    }
+
+    /// Watches resources of this kind for changes
+    pub fn watch(&self, query: Option<&ListQuery>) -> Result<WatchEvents<DynamicObject>> {
+        let mut route = self.kind_route(self.list_ns());
+        let mut pairs = query.map(ListQuery::as_query_pairs).unwrap_or_default();
+        pairs.insert("watch", "true".to_owned());
+        route.query(pairs);
+        self.kube.low_level.watch(&route)
+    }
+
+    /// Creates a resource, filling in its `apiVersion` and `kind` if unset
+    pub fn create(&self, object: &DynamicObject) -> Result<DynamicObject> {
+        let route = self.kind_route(self.ns());
+        let object = object.clone().with_types(&self.resource);
+        self.kube.low_level.apply(&route, &object)
+    }
+
+    /// Replaces the named resource, conditional on `metadata.resourceVersion` when it is set
+    pub fn update(&self, object: &DynamicObject) -> Result<DynamicObject> {
+        let name = object.metadata.name.as_ref()
+            .chain_err(|| "must set metadata.name to update kubernetes resource")?;
+        let object = object.clone().with_types(&self.resource);
+        self.kube.low_level.replace(&self.resource_route(name), &object)
+    }
+
+    /// Partially updates the named resource
+    pub fn patch(&self, name: &str, patch: &Patch) -> Result<DynamicObject> {
+        self.kube.low_level.patch(&self.resource_route(name), patch)
+    }
+
+    /// Deletes the named resource
+    pub fn delete(&self, name: &str) -> Result<()> {
+        self.kube.low_level.delete(&self.resource_route(name))
+    }
+
+    /// Deletes the named resource with options, e.g. a propagation policy
+    pub fn delete_with(&self, name: &str, params: &DeleteParams) -> Result<DeleteResponse<DynamicObject>> {
+        DeleteResponse::from_value(self.kube.low_level.delete_with(&self.resource_route(name), params)?)
+    }
+
+    // Cluster-scoped kinds have no namespace, others default to the "default" namespace
+    fn ns(&self) -> Option<&str> {
+        if !self.resource.namespaced {
+            return None;
+        }
+        Some(self.kube.namespace.as_deref().unwrap_or("default"))
+    }
+
+    fn list_ns(&self) -> Option<&str> {
+        if self.kube.all_namespaces {
+            None
+        } else {
+            self.ns()
+        }
+    }
+
+    fn kind_route<'a>(&'a self, namespace: Option<&'a str>) -> KindRoute<'a> {
+        let mut route = KindRoute::new(&self.api, &self.resource.plural);
+        if let Some(ns) = namespace {
+            route.namespace(ns);
+        }
+        route
+    }
+
+    fn resource_route<'a>(&'a self, name: &'a str) -> ResourceRoute<'a> {
+        let mut route = ResourceRoute::new(&self.api, &self.resource.plural, name);
+        if let Some(ns) = self.ns() {
+            route.namespace(ns);
+        }
+        route
+    }
+}
@@ -3,7 +3,6 @@ use std::path::Path;
 use std::mem;
 use config::KubeConfig;
 use config::AuthInfo;
-use resources::*;
 use std::fs::File;
 use std::io::Read;
 use openssl::pkcs12::Pkcs12;
@@ -12,13 +11,15 @@ use serde::de::DeserializeOwned;
 use serde_json::{self, Value};
 use serde_yaml;
 use url::Url;
-use std::borrow::Borrow;
+use std::borrow::{Borrow, Cow};
 use walkdir::WalkDir;
 use errors::*;
+use resources::ApiResource;
+use super::{ApplyParams, DeleteParams, Patch, WatchEvents, LAST_APPLIED_ANNOTATION};
 use k8s_api::apimachinery::pkg::apis::meta::v1::ObjectMeta;
 use reqwest::async::{Client, Decoder};
 use std::io::{self, Cursor, Write};
-use futures::{Future, Stream};
+use futures::{future, Future, Stream};
 use colored::*;
 use rand::Rng;
 use rand::thread_rng;
@@ -32,15 +33,19 @@ pub struct KubeLowLevel {
     pub(crate) async_client: reqwest::async::Client,
     pub(crate) auth_info: AuthInfo,
     pub(crate) base_url: Url,
+    pub(crate) dry_run: bool,
 }
 
+/// The JSON response of an asynchronous request
+pub type JsonFuture = Box<dyn Future<Item = Value, Error = Error> + Send>;
+
 // This is only used for figuring out the API endpoint to use
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
-struct MinimalResource {
-    api_version: String,
-    kind: Kind,
-    metadata: ObjectMeta,
+pub(crate) struct MinimalResource {
+    pub(crate) api_version: String,
+    pub(crate) kind: String,
+    pub(crate) metadata: ObjectMeta,
 }
 
 
@@ -105,7 +110,8 @@ impl KubeLowLevel {
             client: client,
             async_client: async_client,
             auth_info: auth_info,
-            base_url: cluster.server
+            base_url: cluster.server,
+            dry_run: false,
         })
     }
 
@@ -157,9 +163,7 @@ impl KubeLowLevel {
             StatusCode::NOT_FOUND => Ok(false),
             s if s.is_success() => Ok(true),
             _ => {
-                let status: Status = response.json()
-                    .chain_err(|| "Failed to decode error response as 'Status'")?;
-                bail!(status.message);
+                bail!(api_error(response));
             }
         }
     }
@@ -170,6 +174,13 @@ impl KubeLowLevel {
         self.http_get_json(url)
     }
 
+    pub fn list_future(&self, route: &KindRoute) -> JsonFuture {
+        match route.build(&self.base_url) {
+            Ok(url) => self.http_get_json_future(url),
+            Err(e) => Box::new(future::err(e)),
+        }
+    }
+
     pub fn get<D>(&self, route: &ResourceRoute) -> Result<D>
     where D: DeserializeOwned,
     {
@@ -177,6 +188,14 @@ impl KubeLowLevel {
         self.http_get_json(url)
     }
 
+    pub fn watch<D>(&self, route: &KindRoute) -> Result<WatchEvents<D>>
+    where D: DeserializeOwned,
+    {
+        let url = route.build(&self.base_url)?;
+        let response = self.http_get(url)?;
+        Ok(WatchEvents::new(response))
+    }
+
     pub fn get_async(&self, route: &ResourceRoute) -> Result<()> {
         let url = route.build(&self.base_url)?;
         self.http_get_text(url, route.resource.to_string())
@@ -206,6 +225,21 @@ impl KubeLowLevel {
         self.http_post_json(url, &body)
     }
 
+    pub fn replace<S, D>(&self, route: &ResourceRoute, body: &S) -> Result<D>
+    where S: Serialize,
+          D: DeserializeOwned
+    {
+        let url = route.build(&self.base_url)?;
+        self.http_put_json(url, &body)
+    }
+
+    pub fn patch<D>(&self, route: &ResourceRoute, patch: &Patch) -> Result<D>
+    where D: DeserializeOwned
+    {
+        let url = route.build(&self.base_url)?;
+        self.http_patch_json(url, patch)
+    }
+
     pub(crate) fn each_resource_path<D, F, P: AsRef<Path>>(&self, path: P, handler: F) -> Result<Vec<D>>
     where
         D: DeserializeOwned + ::std::fmt::Debug,
@@ -225,86 +259,75 @@ impl KubeLowLevel {
             .collect()
     }
 
-    // TODO: This function could use a serious refactoring
-    pub(crate) fn apply_file<D>(&self, path: &Path) -> Result<D>
+    // Creates the resource, or updates it with a three-way merge between the last applied
+    // configuration, the manifest and the live resource, strategic for built-in kinds
+    // (like `kubectl apply`)
+    pub(crate) fn apply_manifest<D>(&self, mut body: Value, resource: &ApiResource, namespace: &str) -> Result<D>
     where D: DeserializeOwned + ::std::fmt::Debug
     {
-        let mut bytes = Vec::new();
-        let ext = path.extension().unwrap().to_string_lossy().to_lowercase();
-        let mut file = File::open(path)?;
-        file.read_to_end(&mut bytes)?;
-        let body: Value = match &*ext {
-            "json" => serde_json::from_slice(&bytes)?,
-            "yaml" => serde_yaml::from_slice(&bytes)?,
-            _ => unreachable!("kubeclient bug: unexpected and unfiltered file extension"),
-        };
-        let mini: MinimalResource = serde_json::from_value(body.clone())?;
-
-        let root = if mini.api_version.starts_with("v") {
-            "/api"
-        } else {
-            "/apis"
-        };
-        let name = mini.metadata.name.expect("must set metadata.name to apply kubernetes resource");
-        let kind_path = match mini.metadata.namespace.as_ref().map(|x| &**x).borrow().or(mini.kind.default_namespace) {
-            Some(ns) => format!("{}/{}/namespaces/{}/{}", root, mini.api_version, ns, mini.kind.plural),
-            None =>format!("{}/{}/{}", root, mini.api_version, mini.kind.plural),
-        };
-        let kind_url = self.base_url.join(&kind_path)?;
-        let resource_url = self.base_url.join(&format!("{}/{}", kind_path, name))?;
+        let (kind_url, resource_url) = self.manifest_urls(&body, resource, namespace)?;
+        set_last_applied(&mut body)?;
 
         // First check if resource already exists
-        let mut response = self.auth(self.client.get(resource_url)).send()
+        let mut response = self.auth(self.client.get(resource_url.clone())).send()
             .chain_err(|| "Failed to GET URL")?;
         match response.status() {
-            // Apply if resource doesn't exist
+            // Create if resource doesn't exist
             StatusCode::NOT_FOUND => {
                 let resp = self.http_post_json(kind_url, &body)?;
                 Ok(resp)
             }
-            // Return it if it already exists
+            // Patch it if it already exists
             s if s.is_success() => {
-                let resp = response.json().chain_err(|| "Failed to decode JSON response")?;
-                Ok(resp)
+                let current: Value = response.json().chain_err(|| "Failed to decode JSON response")?;
+                let original = current.pointer("/metadata/annotations")
+                    .and_then(|annotations| annotations.get(LAST_APPLIED_ANNOTATION))
+                    .and_then(Value::as_str)
+                    .and_then(|last_applied| serde_json::from_str::<Value>(last_applied).ok());
+                let patch = if resource.accepts_strategic_merge() {
+                    Patch::three_way_strategic(original.as_ref(), &body, &current)
+                } else {
+                    Patch::three_way_merge(original.as_ref(), &body, &current)
+                };
+                if patch.is_empty() {
+                    return Ok(serde_json::from_value(current)?);
+                }
+                self.http_patch_json(resource_url, &patch)
             }
             // Propogate any other error
             _ => {
-                let status: Status = response.json()
-                    .chain_err(|| "Failed to decode error response as 'Status'")?;
-                bail!(status.message);
+                bail!(api_error(response));
             }
         }
     }
 
-    pub(crate) fn replace_file<D>(&self, path: &Path) -> Result<D>
+    pub(crate) fn server_side_apply_manifest<D>(&self, body: Value, params: &ApplyParams, resource: &ApiResource, namespace: &str) -> Result<D>
     where D: DeserializeOwned + ::std::fmt::Debug
     {
-        let mut bytes = Vec::new();
-        let ext = path.extension().unwrap().to_string_lossy().to_lowercase();
-        let mut file = File::open(path)?;
-        file.read_to_end(&mut bytes)?;
-        let body: Value = match &*ext {
-            "json" => serde_json::from_slice(&bytes)?,
-            "yaml" => serde_yaml::from_slice(&bytes)?,
-            _ => unreachable!("kubeclient bug: unexpected and unfiltered file extension"),
-        };
+        let (_, mut resource_url) = self.manifest_urls(&body, resource, namespace)?;
+        resource_url.query_pairs_mut().extend_pairs(params.as_query_pairs());
+        self.http_patch_json(resource_url, &Patch::Apply(body))
+    }
+
+    // Builds the URLs of the kind and of the resource described by a manifest,
+    // which is in `namespace` unless it sets its own
+    fn manifest_urls(&self, body: &Value, resource: &ApiResource, namespace: &str) -> Result<(Url, Url)> {
         let mini: MinimalResource = serde_json::from_value(body.clone())?;
 
-        let root = if mini.api_version.starts_with("v") {
-            "/api"
-        } else {
-            "/apis"
-        };
-        let name = mini.metadata.name.expect("must set metadata.name to apply kubernetes resource");
-        let url = match mini.metadata.namespace {
-            Some(ns) => self.base_url.join(
-                &format!("{}/{}/namespaces/{}/{}/{}", root, mini.api_version, ns, mini.kind.plural, name)
-                )?,
-            None => self.base_url.join(
-                &format!("{}/{}/{}/{}", root, mini.api_version, mini.kind.plural, name)
-                )?,
-        };
-        let resp = self.http_put_json(url, &body)?;
+        let name = mini.metadata.name
+            .chain_err(|| "must set metadata.name to apply kubernetes resource")?;
+        let namespace = mini.metadata.namespace.as_deref().unwrap_or(namespace);
+        let kind_path = resource.path(Some(namespace));
+        let kind_url = self.base_url.join(&kind_path)?;
+        let resource_url = self.base_url.join(&format!("{}/{}", kind_path, name))?;
+        Ok((kind_url, resource_url))
+    }
+
+    pub(crate) fn replace_manifest<D>(&self, body: Value, resource: &ApiResource, namespace: &str) -> Result<D>
+    where D: DeserializeOwned + ::std::fmt::Debug
+    {
+        let (_, resource_url) = self.manifest_urls(&body, resource, namespace)?;
+        let resp = self.http_put_json(resource_url, &body)?;
         Ok(resp)
     }
 
@@ -313,6 +336,16 @@ impl KubeLowLevel {
         self.http_delete(url).map(|_| ())
     }
 
+    pub fn delete_with(&self, route: &ResourceRoute, params: &DeleteParams) -> Result<Value> {
+        let url = route.build(&self.base_url)?;
+        self.http_delete_json(url, &params.to_body()?)
+    }
+
+    pub fn delete_collection(&self, route: &KindRoute, params: &DeleteParams) -> Result<Value> {
+        let url = route.build(&self.base_url)?;
+        self.http_delete_json(url, &params.to_body()?)
+    }
+
     //
     // Low-level
     //
@@ -366,9 +399,7 @@ impl KubeLowLevel {
         let mut response = req.send().chain_err(|| "Failed to GET URL")?;
 
         if !response.status().is_success() {
-            let status: Status = response.json()
-                .chain_err(|| "Failed to decode kubernetes error response as 'Status'")?;
-            bail!(format!("Kubernetes API error: {}", status.message));
+            bail!(api_error(response));
         }
         Ok(response)
     }
@@ -387,21 +418,45 @@ impl KubeLowLevel {
         Ok(fut)
     }
 
+    pub(crate) fn http_get_json_future(&self, url: Url) -> JsonFuture {
+        let fut = self.auth_async(self.async_client.get(url)).send()
+            .map_err(|e| Error::with_chain(e, "Failed to GET URL"))
+            .and_then(|response| {
+                let status = response.status();
+                response.into_body().concat2()
+                    .map_err(|e| Error::with_chain(e, "Failed to read response"))
+                    .and_then(move |body| {
+                        if !status.is_success() {
+                            let body = String::from_utf8_lossy(&body);
+                            bail!(ErrorKind::from(ApiError::from_response_body(status.as_u16(), &body)));
+                        }
+                        Ok(serde_json::from_slice(&body).chain_err(|| "Failed to decode JSON response")?)
+                    })
+            });
+        Box::new(fut)
+    }
+
 
 
+    // Mutating requests are only validated by the server when in dry-run mode
+    fn mutation_url(&self, mut url: Url) -> Url {
+        if self.dry_run {
+            url.query_pairs_mut().append_pair("dryRun", "All");
+        }
+        url
+    }
+
     pub(crate) fn http_post_json<S, D>(&self, url: Url, body: &S) -> Result<D>
     where S: Serialize,
           D: DeserializeOwned,
     {
-        let mut response = self.auth(self.client.post(url))
+        let mut response = self.auth(self.client.post(self.mutation_url(url)))
             .json(&body)
             .send()
             .chain_err(|| "Failed to POST URL")?;
 
         if !response.status().is_success() {
-            let status: Status = response.json()
-                .chain_err(|| "Failed to decode kubernetes error response as 'Status'")?;
-            bail!(format!("Kubernetes API error: {}", status.message));
+            bail!(api_error(response));
         }
 
         Ok(response.json().chain_err(|| "Failed to decode JSON response")?)
@@ -411,59 +466,137 @@ impl KubeLowLevel {
     where S: Serialize,
           D: DeserializeOwned,
     {
-        let mut response = self.auth(self.client.put(url))
+        let mut response = self.auth(self.client.put(self.mutation_url(url)))
             .json(&body)
             .send()
             .chain_err(|| "Failed to PUT URL")?;
 
         if !response.status().is_success() {
-            let status: Status = response.json()
-                .chain_err(|| "Failed to decode kubernetes error response as 'Status'")?;
-            bail!(format!("Kubernetes API error: {}", status.message));
+            bail!(api_error(response));
+        }
+
+        Ok(response.json().chain_err(|| "Failed to decode JSON response")?)
+    }
+
+    pub(crate) fn http_patch_json<D>(&self, url: Url, patch: &Patch) -> Result<D>
+    where D: DeserializeOwned,
+    {
+        let mut response = self.auth(self.client.patch(self.mutation_url(url)))
+            .header(header::CONTENT_TYPE, patch.content_type())
+            .body(patch.to_body()?)
+            .send()
+            .chain_err(|| "Failed to PATCH URL")?;
+
+        if !response.status().is_success() {
+            bail!(api_error(response));
         }
 
         Ok(response.json().chain_err(|| "Failed to decode JSON response")?)
     }
 
     pub(crate) fn http_delete(&self, url: Url) -> Result<reqwest::Response> {
-        let mut response = self.auth(self.client.delete(url))
+        let response = self.auth(self.client.delete(self.mutation_url(url)))
             .send()
             .chain_err(|| "Failed to DELETE URL")?;
 
         if !response.status().is_success() {
-            let status: Status = response.json()
-                .chain_err(|| "Failed to decode kubernetes error response as 'Status'")?;
-            bail!(format!("Kubernetes API error: {}", status.message));
+            bail!(api_error(response));
         }
 
         Ok(response)
     }
 
+    pub(crate) fn http_delete_json<S, D>(&self, url: Url, body: &S) -> Result<D>
+    where S: Serialize,
+          D: DeserializeOwned,
+    {
+        let mut response = self.auth(self.client.delete(self.mutation_url(url)))
+            .json(&body)
+            .send()
+            .chain_err(|| "Failed to DELETE URL")?;
+
+        if !response.status().is_success() {
+            bail!(api_error(response));
+        }
+
+        Ok(response.json().chain_err(|| "Failed to decode JSON response")?)
+    }
+
+}
+
+
+// Decodes a failed response into an `ErrorKind::Api`, or an `ErrorKind::ApplyConflict`
+// when a server-side apply conflicts with other field managers
+pub(crate) fn api_error(mut response: reqwest::Response) -> ErrorKind {
+    let code = response.status().as_u16();
+    let mut body = String::new();
+    let _ = response.read_to_string(&mut body);
+    let err = ApiError::from_response_body(code, &body);
+    let conflicts = err.field_manager_conflicts();
+    if conflicts.is_empty() {
+        err.into()
+    } else {
+        ErrorKind::ApplyConflict(Box::new(err), conflicts)
+    }
+}
+
+// Records a manifest in its own last applied configuration annotation
+fn set_last_applied(body: &mut Value) -> Result<()> {
+    let last_applied = serde_json::to_string(body)?;
+    let metadata = body.as_object_mut()
+        .chain_err(|| "resource manifest must be an object")?
+        .entry("metadata")
+        .or_insert_with(|| Value::Object(Default::default()));
+    let annotations = metadata.as_object_mut()
+        .chain_err(|| "metadata of resource manifest must be an object")?
+        .entry("annotations")
+        .or_insert_with(|| Value::Object(Default::default()));
+    if annotations.is_null() {
+        *annotations = Value::Object(Default::default());
+    }
+    annotations.as_object_mut()
+        .chain_err(|| "metadata.annotations of resource manifest must be an object")?
+        .insert(LAST_APPLIED_ANNOTATION.to_owned(), Value::String(last_applied));
+    Ok(())
 }
 
+// Reads a JSON or YAML resource file
+pub(crate) fn read_manifest(path: &Path) -> Result<Value> {
+    let mut bytes = Vec::new();
+    let ext = path.extension().unwrap().to_string_lossy().to_lowercase();
+    let mut file = File::open(path)?;
+    file.read_to_end(&mut bytes)?;
+    let body: Value = match &*ext {
+        "json" => serde_json::from_slice(&bytes)?,
+        "yaml" => serde_yaml::from_slice(&bytes)?,
+        _ => unreachable!("kubeclient bug: unexpected and unfiltered file extension"),
+    };
+    Ok(body)
+}
 
 pub struct KindRoute<'a> {
-    api: &'a str,
+    api: Cow<'a, str>,
     namespace: Option<&'a str>,
     kind: &'a str,
     query: Option<Vec<(String, String)>>,
 }
 
 pub struct ResourceRoute<'a> {
-    api: &'a str,
+    api: Cow<'a, str>,
     namespace: Option<&'a str>,
     kind: &'a str,
     resource: &'a str,
     logs: Option<bool>,
     subresource: Option<&'a str>,
+    container: Option<&'a str>,
     query: Option<Vec<(String, String)>>,
 }
 
 
 impl<'a> KindRoute<'a> {
-    pub fn new(api: &'a str, kind: &'a str) -> KindRoute<'a> {
+    pub fn new<A: Into<Cow<'a, str>>>(api: A, kind: &'a str) -> KindRoute<'a> {
         KindRoute {
-            api, kind,
+            api: api.into(), kind,
             namespace: None,
             query: None,
         }
@@ -506,13 +639,14 @@ impl<'a> KindRoute<'a> {
 }
 
 impl<'a> ResourceRoute<'a> {
-    pub fn new(api: &'a str, kind: &'a str, resource: &'a str) -> ResourceRoute<'a> {
+    pub fn new<A: Into<Cow<'a, str>>>(api: A, kind: &'a str, resource: &'a str) -> ResourceRoute<'a> {
         ResourceRoute {
-            api, kind, resource,
+            api: api.into(), kind, resource,
             namespace: None,
             query: None,
             logs: None,
-            subresource: None
+            subresource: None,
+            container: None,
         }
     }
 
@@ -526,50 +660,54 @@ impl<'a> ResourceRoute<'a> {
         self
     }
 
+    /// Targets a subresource, e.g. `status` or `scale`
     pub fn subresource(&mut self, subresource: &'a str) -> &mut ResourceRoute<'a> {
         self.subresource = Some(subresource);
         self
     }
 
-    // pub fn query<I, K, V>(&mut self, query: I) -> &mut ResourceRoute<'a>
-    // where
-    //     I: IntoIterator,
-    //     I::Item: Borrow<(K, V)>,
-    //     K: AsRef<str>,
-    //     V: AsRef<str>,
-    // {
-    //     // This is ugly, but today the borrow checker beat me
-    //     let pairs = query.into_iter()
-    //         .map(|i| {
-    //             let (ref k, ref v) = *i.borrow();
-    //             (k.as_ref().to_owned(), v.as_ref().to_owned())
-    //         })
-    //         .collect();
-    //     self.query = Some(pairs);
-    //     self
-    // }
+    /// Selects the container whose logs are read
+    pub fn container(&mut self, container: &'a str) -> &mut ResourceRoute<'a> {
+        self.container = Some(container);
+        self
+    }
+
+    pub fn query<I, K, V>(&mut self, query: I) -> &mut ResourceRoute<'a>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<(K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        // This is ugly, but today the borrow checker beat me
+        let pairs = query.into_iter()
+            .map(|i| {
+                let (ref k, ref v) = *i.borrow();
+                (k.as_ref().to_owned(), v.as_ref().to_owned())
+            })
+            .collect();
+        self.query = Some(pairs);
+        self
+    }
 
     pub(crate) fn build(&self, base_url: &Url) -> Result<Url> {
-        let path = match self.logs {
-            Some(true) =>
-                match self.namespace {
-                    Some(ns) =>
-                        match self.subresource {
-                            Some(sr) => format!("{}/namespaces/{}/{}/{}/log?follow=1&container={}", self.api, ns, self.kind, self.resource, sr),
-                            _ => format!("{}/namespaces/{}/{}/{}/log?follow=1", self.api, ns, self.kind, self.resource),
-                        }
-                    None =>
-                        match self.subresource {
-                            Some(sr) => format!("{}/namespaces/{}/{}/{}/log?follow=1&container={}", self.api, "default", self.kind, self.resource, sr),
-                            _ => format!("{}/namespaces/{}/{}/{}/log?follow=1", self.api, "default", self.kind, self.resource),
-                        }
+        let mut path = match self.namespace {
+            Some(ns) => format!("{}/namespaces/{}/{}/{}", self.api, ns, self.kind, self.resource),
+            None => format!("{}/{}/{}", self.api, self.kind, self.resource),
+        };
+        match self.logs {
+            Some(true) => {
+                path.push_str("/log?follow=1");
+                if let Some(container) = self.container {
+                    path.push_str(&format!("&container={}", container));
                 }
-            _ =>
-                match self.namespace {
-                    Some(ns) => format!("{}/namespaces/{}/{}/{}", self.api, ns, self.kind, self.resource),
-                    None => format!("{}/{}/{}", self.api, self.kind, self.resource),
+            }
+            _ => {
+                if let Some(subresource) = self.subresource {
+                    path.push_str(&format!("/{}", subresource));
                 }
-        };
+            }
+        }
 
         let mut url = base_url.join(&path)?;
         if let Some(ref query) = self.query {
@@ -578,3 +716,56 @@ impl<'a> ResourceRoute<'a> {
         Ok(url)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake_server::FakeServer;
+    use serde_json::json;
+
+    #[test]
+    fn subresources_are_appended_to_the_resource_path() {
+        let base = Url::parse("https://cluster.example/").unwrap();
+        let url = ResourceRoute::new("apis/apps/v1", "deployments", "web")
+            .namespace("prod")
+            .subresource("status")
+            .build(&base)
+            .unwrap();
+        assert_eq!(url.as_str(), "https://cluster.example/apis/apps/v1/namespaces/prod/deployments/web/status");
+    }
+
+    #[test]
+    fn logs_select_a_container() {
+        let base = Url::parse("https://cluster.example/").unwrap();
+        let url = ResourceRoute::new("api/v1", "pods", "web-0")
+            .namespace("prod")
+            .logs()
+            .container("nginx")
+            .build(&base)
+            .unwrap();
+        assert_eq!(url.as_str(), "https://cluster.example/api/v1/namespaces/prod/pods/web-0/log?follow=1&container=nginx");
+    }
+
+    #[test]
+    fn manifests_default_to_the_given_namespace() {
+        let low_level = FakeServer::start().kube().low_level;
+        let config_maps = ApiResource::new("", "v1", "ConfigMap", "configmaps", true);
+        let manifest = json!({ "apiVersion": "v1", "kind": "ConfigMap", "metadata": { "name": "settings" } });
+        let (kind_url, resource_url) = low_level.manifest_urls(&manifest, &config_maps, "staging").unwrap();
+        assert_eq!(kind_url.path(), "/api/v1/namespaces/staging/configmaps");
+        assert_eq!(resource_url.path(), "/api/v1/namespaces/staging/configmaps/settings");
+
+        let manifest = json!({ "apiVersion": "v1", "kind": "ConfigMap", "metadata": { "name": "settings", "namespace": "prod" } });
+        let (_, resource_url) = low_level.manifest_urls(&manifest, &config_maps, "staging").unwrap();
+        assert_eq!(resource_url.path(), "/api/v1/namespaces/prod/configmaps/settings");
+    }
+
+    #[test]
+    fn manifests_without_a_name_are_rejected() {
+        let low_level = FakeServer::start().kube().low_level;
+        let config_maps = ApiResource::new("", "v1", "ConfigMap", "configmaps", true);
+        let manifest = json!({ "apiVersion": "v1", "kind": "ConfigMap", "metadata": {} });
+        let err = low_level.manifest_urls(&manifest, &config_maps, "default").unwrap_err();
+        assert_eq!(err.to_string(), "must set metadata.name to apply kubernetes resource");
+    }
+}
@@ -1,23 +1,42 @@
 mod low_level;
 mod resource_clients;
+mod delete;
+mod dynamic;
+mod pager;
+mod patch;
+mod watch;
 
 pub use self::resource_clients::*;
+pub use self::delete::*;
+pub use self::dynamic::*;
+pub use self::pager::*;
+pub use self::patch::*;
+pub use self::watch::*;
 use self::low_level::*;
 
 use std::path::Path;
 use resources::*;
-use serde_json::Value;
+use serde_json::{self, Value};
 use errors::*;
+use discovery::{Discovery, DiscoveryCache, RestMapper, ServerVersion};
 use std::marker::PhantomData;
+use std::cell::{Cell, RefCell};
+use std::thread;
+use std::time::{Duration, Instant};
 use reqwest::async::RequestBuilder;
+use futures::{stream, Future, Stream};
 
+// How many times `get_modify_update` reads and modifies a resource again after a conflict
+const MAX_CONFLICT_RETRIES: u32 = 5;
 
 /// The main type for instantiating clients for managing kubernetes resources
 #[derive(Clone)]
 pub struct Kubernetes {
     pub(crate) low_level: KubeLowLevel,
     namespace: Option<String>,
+    all_namespaces: bool,
     logs: Option<bool>,
+    discovery_cache: Option<DiscoveryCache>,
 }
 
 impl Kubernetes {
@@ -35,9 +54,12 @@ impl Kubernetes {
     /// let kube = Kubernetes::load_conf("admin.conf")?;
     /// ```
     pub fn load_conf<P: AsRef<Path>>(path: P) -> Result<Kubernetes> {
+        let low_level = KubeLowLevel::load_conf(path)?;
         Ok(Kubernetes{
-            low_level: KubeLowLevel::load_conf(path)?,
+            discovery_cache: DiscoveryCache::for_server(&low_level.base_url),
+            low_level,
             namespace: None,
+            all_namespaces: false,
             logs: None,
         })
     }
@@ -56,9 +78,12 @@ impl Kubernetes {
     /// let kube = Kubernetes::load_conf("admin.conf")?;
     /// ```
     pub fn load_conf_with_ctx<P: AsRef<Path>>(path: P, ctxname: &str) -> Result<Kubernetes> {
+        let low_level = KubeLowLevel::load_conf_with_ctx(path, ctxname)?;
         Ok(Kubernetes{
-            low_level: KubeLowLevel::load_conf_with_ctx(path, ctxname)?,
+            discovery_cache: DiscoveryCache::for_server(&low_level.base_url),
+            low_level,
             namespace: None,
+            all_namespaces: false,
             logs: None,
         })
     }
@@ -78,6 +103,22 @@ impl Kubernetes {
         KubeClient { kube: self.clone(), _marker: PhantomData }
     }
 
+    /// Get a kubernetes client for managing `CustomResourceDefinitions`
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// if kube.custom_resource_definitions().exists("certificates.cert-manager.io")? {
+    ///     println!("Found cert-manager certificates")
+    /// }
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn custom_resource_definitions(&self) -> KubeClient<CustomResourceDefinition> {
+        KubeClient { kube: self.clone(), _marker: PhantomData }
+    }
+
     /// Get a kubernetes client for managing `Deployments`
     ///
     /// ## Examples
@@ -93,6 +134,58 @@ impl Kubernetes {
         KubeClient { kube: self.clone(), _marker: PhantomData }
     }
 
+    /// Get a kubernetes client for managing resources of a kind only known at runtime,
+    /// e.g. a custom resource
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// # use qube::resources::ApiResource;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// let workflows = ApiResource::new("argoproj.io", "v1alpha1", "Workflow", "workflows", true);
+    /// if kube.dynamic(workflows).exists("nightly-build")? {
+    ///     println!("Found 'nightly-build' workflow")
+    /// }
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn dynamic(&self, resource: ApiResource) -> DynamicClient {
+        DynamicClient::new(self, resource)
+    }
+
+    /// Get a kubernetes client for managing `Events`
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// let pod = kube.pods().get("web-server-abcdefgh12345678")?;
+    /// for event in kube.events().for_object(&pod)? {
+    ///     println!("{:?}: {:?}", event.reason, event.message);
+    /// }
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn events(&self) -> KubeClient<Event> {
+        KubeClient { kube: self.clone(), _marker: PhantomData }
+    }
+
+    /// Get a kubernetes client for managing `Leases`
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// if kube.leases().exists("my-controller")? {
+    ///     println!("Found 'my-controller' lease")
+    /// }
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn leases(&self) -> KubeClient<Lease> {
+        KubeClient { kube: self.clone(), _marker: PhantomData }
+    }
+
     /// Get a kubernetes client for managing `NetworkPolicies`
     ///
     /// ## Examples
@@ -138,6 +231,22 @@ impl Kubernetes {
         KubeClient { kube: self.clone(), _marker: PhantomData }
     }
 
+    /// Get a kubernetes client for managing `ReplicaSets`
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// if kube.replica_sets().exists("web-server-5c689d88bb")? {
+    ///     println!("Found 'web-server-5c689d88bb' replica set")
+    /// }
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn replica_sets(&self) -> KubeClient<ReplicaSet> {
+        KubeClient { kube: self.clone(), _marker: PhantomData }
+    }
+
     /// Get a kubernetes client for managing `Secrets`
     ///
     /// ## Examples
@@ -168,6 +277,40 @@ impl Kubernetes {
         KubeClient { kube: self.clone(), _marker: PhantomData }
     }
 
+    /// Get a kubernetes client for managing `StatefulSets`
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// if kube.stateful_sets().exists("database")? {
+    ///     println!("Found 'database' stateful set")
+    /// }
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn stateful_sets(&self) -> KubeClient<StatefulSet> {
+        KubeClient { kube: self.clone(), _marker: PhantomData }
+    }
+
+    /// Get a kubernetes client for managing any kind of resource,
+    /// including your own types implementing `Resource`
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// # use qube::resources::Deployment;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// if kube.client::<Deployment>().exists("web-server")? {
+    ///     println!("Found 'web-server' deployment")
+    /// }
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn client<R: Resource>(&self) -> KubeClient<R> {
+        KubeClient { kube: self.clone(), _marker: PhantomData }
+    }
+
     /// Get a kubernetes client that uses a specific namespace
     ///
     /// ## Examples
@@ -180,7 +323,26 @@ impl Kubernetes {
     ///     .get("clusterinfo")?;
     /// ```
     pub fn namespace(&self, namespace: &str) -> Kubernetes {
-        Kubernetes { low_level: self.low_level.clone(), namespace: Some(namespace.to_owned()), logs: self.logs }
+        Kubernetes { namespace: Some(namespace.to_owned()), all_namespaces: false, logs: self.logs, ..self.clone() }
+    }
+
+    /// Get a kubernetes client that lists and watches resources across all namespaces
+    ///
+    /// Only lists and watches are cluster-wide; other calls still use the default
+    /// namespace of the resource type. Listed resources carry their own `metadata.namespace`.
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// for pod in kube.all_namespaces().pods().list(None)? {
+    ///     println!("{:?}/{:?}", pod.metadata.namespace, pod.metadata.name);
+    /// }
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn all_namespaces(&self) -> Kubernetes {
+        Kubernetes { namespace: None, all_namespaces: true, ..self.clone() }
     }
 
     /// Get a kubernetes client that uses a specific namespace
@@ -195,7 +357,146 @@ impl Kubernetes {
     ///     .get("clusterinfo")?;
     /// ```
     pub fn logs(&self) -> Kubernetes {
-        Kubernetes { low_level: self.low_level.clone(), namespace: self.namespace.clone(), logs: Some(true) }
+        Kubernetes { logs: Some(true), ..self.clone() }
+    }
+
+    /// Get a kubernetes client that only validates mutating calls, without persisting them
+    ///
+    /// This is similar to the `--dry-run=server` option of `kubectl` CLI commands.
+    ///
+    /// `create`, `apply`, `replace`, `update`, `patch`, `delete` and `scale` are sent
+    /// with `dryRun=All`, and return the resource as the server would have stored it
+    /// (i.e. defaulted and validated). `delete` returns nothing, while `delete_with`
+    /// returns what would have been deleted.
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// kube.dry_run().apply("web-server/deployment.yaml")?;
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn dry_run(&self) -> Kubernetes {
+        let mut kube = self.clone();
+        kube.low_level.dry_run = true;
+        kube
+    }
+
+    /// Indicates whether this client is in dry-run mode (see `dry_run`)
+    pub fn is_dry_run(&self) -> bool {
+        self.low_level.dry_run
+    }
+
+    /// Discovers the API groups, versions and resources served by the cluster
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// let discovery = kube.discovery()?;
+    /// for resource in discovery.resources("apps/v1") {
+    ///     println!("{} ({})", resource.name, resource.verbs.join(", "));
+    /// }
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn discovery(&self) -> Result<Discovery> {
+        if let Some(ref cache) = self.discovery_cache {
+            if let Some(discovery) = cache.load() {
+                return Ok(discovery);
+            }
+        }
+        self.refresh_discovery()
+    }
+
+    /// Discovers the API groups, versions and resources served by the cluster,
+    /// bypassing and then updating the discovery cache
+    pub fn refresh_discovery(&self) -> Result<Discovery> {
+        let discovery = Discovery::run(self)?;
+        if let Some(ref cache) = self.discovery_cache {
+            // Caching is an optimization, failing to write the cache is not an error
+            let _ = cache.invalidate().and_then(|_| cache.store(&discovery));
+        }
+        Ok(discovery)
+    }
+
+    /// Get a kubernetes client that caches discovery results in `cache`, or not at all
+    ///
+    /// By default, discovery is cached where `kubectl` caches it
+    /// (`~/.kube/cache/discovery/<host>_<port>/`) for 6 hours.
+    pub fn discovery_cache(&self, cache: Option<DiscoveryCache>) -> Kubernetes {
+        Kubernetes { discovery_cache: cache, ..self.clone() }
+    }
+
+    /// Discovers the kinds served by the cluster and maps them to their URLs
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// let mapper = kube.rest_mapper()?;
+    /// if let Some(deployments) = mapper.resource_for("deploy") {
+    ///     println!("{}", deployments.path(Some("default")));
+    /// }
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn rest_mapper(&self) -> Result<RestMapper> {
+        Ok(self.discovery()?.rest_mapper())
+    }
+
+    /// Installs or updates the CRD of a custom resource kind, then waits until it is established
+    ///
+    /// Fails if the kind's names conflict with another CRD, or if it isn't established within `timeout`.
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # #[macro_use] extern crate serde_derive;
+    /// # #[macro_use] extern crate qube_derive;
+    /// # extern crate qube;
+    /// # use qube::prelude::*;
+    /// # use std::time::Duration;
+    /// # #[derive(CustomResource, JsonSchema, Serialize, Deserialize, Clone, Debug)]
+    /// # #[resource(group = "example.com", version = "v1", kind = "Backup", namespaced)]
+    /// # pub struct BackupSpec { schedule: String }
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// kube.ensure_crd::<Backup>(Duration::from_secs(30))?;
+    /// let backups = kube.client::<Backup>().list(None)?;
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn ensure_crd<R: CustomResourceExt>(&self, timeout: Duration) -> Result<CustomResourceDefinition> {
+        let crds = self.custom_resource_definitions();
+        let mut crd = R::crd();
+        let name = crd.metadata.name.clone()
+            .chain_err(|| "must set metadata.name to install a CRD")?;
+        if crds.exists(&name)? {
+            let current = crds.get(&name)?;
+            crd.metadata.resource_version = current.metadata.resource_version;
+            crds.update(&crd)?;
+        } else {
+            crds.create(&crd)?;
+        }
+        if let Some(ref cache) = self.discovery_cache {
+            // The cached discovery doesn't know about the new kind yet
+            let _ = cache.invalidate();
+        }
+
+        let start = Instant::now();
+        loop {
+            let crd = crds.get(&name)?;
+            if crd.is_established() {
+                return Ok(crd);
+            }
+            if let Some(condition) = crd.condition("NamesAccepted").filter(|c| c.status == "False") {
+                bail!("CRD {} names are not accepted: {}", name, condition.message.as_deref().unwrap_or("no reason given"));
+            }
+            if start.elapsed() >= timeout {
+                bail!("CRD {} is not established after {:?}", name, timeout);
+            }
+            thread::sleep(READY_POLL_INTERVAL);
+        }
     }
 
     /// Check to see if the Kubernetes API is healthy
@@ -211,13 +512,74 @@ impl Kubernetes {
         Ok(self.low_level.health()? == "ok")
     }
 
+    /// Reads the version of the API server
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// let version = kube.server_version()?;
+    /// println!("Connected to {} on {}", version.git_version, version.platform);
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn server_version(&self) -> Result<ServerVersion> {
+        let url = self.low_level.base_url.join("version")?;
+        self.low_level.http_get_json(url)
+            .chain_err(|| "Failed to read the server version")
+    }
+
+    /// Checks that the server serves the group version of a typed resource
+    ///
+    /// Without this, a kind the server doesn't serve (e.g. a removed beta API, or a custom
+    /// resource whose CRD isn't installed) only shows up as a 404, like a missing resource.
+    /// This fails with `ErrorKind::NotServed` instead, see `Error::is_not_served`.
+    /// Group versions that could not be discovered are assumed to be served.
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// # use qube::resources::NetworkPolicy;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// if let Err(e) = kube.check_served::<NetworkPolicy>() {
+    ///     eprintln!("warning: {}", e);
+    /// }
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn check_served<R: Resource>(&self) -> Result<()> {
+        let api_version = R::api_version();
+        let mut discovery = self.discovery()?;
+        if !discovery.is_served(&api_version, R::PLURAL) && self.discovery_cache.is_some() {
+            // The cached discovery may predate the kind, e.g. a CRD installed since
+            discovery = self.refresh_discovery()?;
+        }
+        if discovery.is_served(&api_version, R::PLURAL) || discovery.unavailable().contains(&api_version) {
+            return Ok(());
+        }
+        let preferred_version = discovery.preferred_version(R::GROUP).map(|version| match R::GROUP {
+            "" => version.to_owned(),
+            group => format!("{}/{}", group, version),
+        });
+        bail!(ErrorKind::NotServed(R::KIND.to_owned(), api_version, preferred_version))
+    }
+
     /// Applies a JSON or YAML resource file
     ///
     /// This is similar to the `kubectl apply` CLI commands.
     ///
     /// This may be a single file or an entire directory.
-    /// If the resource(s) specified already exists, this method
-    /// will NOT replace the resource.
+    /// If the resource(s) specified already exists, it is patched with a three-way merge
+    /// between the configuration last applied (stored in the
+    /// `kubectl.kubernetes.io/last-applied-configuration` annotation), the file and the
+    /// live resource: fields removed from the file are removed from the resource,
+    /// and fields set by others (e.g. controllers) are left alone. Built-in kinds are
+    /// patched with a strategic merge, which merges lists such as containers by key,
+    /// while custom resources get a JSON merge patch, which replaces lists as a whole.
+    ///
+    /// Resources may be of any kind the cluster serves, including custom resources
+    /// (see `rest_mapper`). Those without a `metadata.namespace` go to the namespace
+    /// of this client, or to `default`.
     ///
     /// ## Examples
     ///
@@ -227,8 +589,40 @@ impl Kubernetes {
     /// let is_healthy = kube.apply("web-server/deployment.yaml")?;
     /// ```
     pub fn apply<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mapper = ManifestMapper::new(self)?;
         let _: Vec<Value> = self.low_level.each_resource_path(path, |path| {
-            self.low_level.apply_file(&path)
+            let manifest = read_manifest(path)?;
+            let resource = mapper.resource_for(&manifest)?;
+            self.low_level.apply_manifest(manifest, &resource, self.manifest_ns())
+                .chain_err(|| format!("Failed to apply {}", path.display()))
+        })?;
+
+        Ok(())
+    }
+
+    /// Applies a JSON or YAML resource file with a server-side apply
+    ///
+    /// This is similar to the `kubectl apply --server-side` CLI commands.
+    ///
+    /// This may be a single file or an entire directory. Unlike `apply`, resources
+    /// that already exist are updated; fields owned by other field managers are only
+    /// taken over when `params.force` is set. Namespaces default like in `apply`.
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// # use qube::clients::ApplyParams;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// kube.server_side_apply("web-server/deployment.yaml", &ApplyParams::new("deployer"))?;
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn server_side_apply<P: AsRef<Path>>(&self, path: P, params: &ApplyParams) -> Result<()> {
+        let mapper = ManifestMapper::new(self)?;
+        let _: Vec<Value> = self.low_level.each_resource_path(path, |path| {
+            let manifest = read_manifest(path)?;
+            let resource = mapper.resource_for(&manifest)?;
+            self.low_level.server_side_apply_manifest(manifest, params, &resource, self.manifest_ns())
                 .chain_err(|| format!("Failed to apply {}", path.display()))
         })?;
 
@@ -241,7 +635,7 @@ impl Kubernetes {
     ///
     /// This may be a single file or an entire directory.
     /// If the resource(s) specified already exists, this method
-    /// will replace the resource.
+    /// will replace the resource. Namespaces default like in `apply`.
     ///
     /// ## Examples
     ///
@@ -251,8 +645,11 @@ impl Kubernetes {
     /// let is_healthy = kube.replace("web-server/deployment.yaml")?;
     /// ```
     pub fn replace<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mapper = ManifestMapper::new(self)?;
         let _: Vec<Value> = self.low_level.each_resource_path(path, |path| {
-            self.low_level.replace_file(&path)
+            let manifest = read_manifest(path)?;
+            let resource = mapper.resource_for(&manifest)?;
+            self.low_level.replace_manifest(manifest, &resource, self.manifest_ns())
                 .chain_err(|| format!("Failed to replace {}", path.display()))
         })?;
 
@@ -277,28 +674,59 @@ impl Kubernetes {
     /// let response = kube.create(&secret)?;
     /// ```
     pub fn create<R: Resource>(&self, resource: &R) -> Result<R> {
-        let mut route = KindRoute::new(R::api(), R::kind().plural);
+        let mut route = KindRoute::new(R::api(), R::PLURAL);
         if let Some(ns) = self.get_ns::<R>() {
             route.namespace(ns);
         }
         self.low_level.apply(&route, resource)
     }
 
+    /// Replaces a resource with a typed resource definition
+    ///
+    /// This is similar to the `kubectl replace` CLI commands.
+    ///
+    /// If `metadata.resourceVersion` is set, the update only succeeds if the resource
+    /// was not modified since that version was read, and fails with a conflict otherwise
+    /// (see `Error::is_conflict`).
+    ///
+    /// The resource is replaced in its `metadata.namespace`, or in the namespace
+    /// of this client when that is unset.
+    ///
+    /// ## Examples:
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// let mut deployment = kube.deployments().get("web-server")?;
+    /// deployment.spec.replicas = Some(3);
+    /// let response = kube.update(&deployment)?;
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn update<R: Resource>(&self, resource: &R) -> Result<R> {
+        let name = resource.metadata().name.as_ref()
+            .chain_err(|| "must set metadata.name to update kubernetes resource")?;
+        let mut route = ResourceRoute::new(R::api(), R::PLURAL, name);
+        if let Some(ns) = self.object_ns(resource) {
+            route.namespace(ns);
+        }
+        self.low_level.replace(&route, resource)
+    }
+
     // Methods below this point are the generic resource read/write methods.
     // They are not exposed publicly, as most of them have no way to infer
     // the generic argument in typical usage, `kube.exists::<Deployment>("web-server")?`
     // is decidedly less ergonomic than `kube.deployments().exists("web-server")?`.
 
-    fn exists<R: Resource>(&self, name: &str) -> Result<bool> {
-        let mut route = ResourceRoute::new(R::api(), R::kind().plural, name);
+    pub(crate) fn exists<R: Resource>(&self, name: &str) -> Result<bool> {
+        let mut route = ResourceRoute::new(R::api(), R::PLURAL, name);
         if let Some(ns) = self.get_ns::<R>() {
             route.namespace(ns);
         }
         self.low_level.exists(&route)
     }
 
-    fn get<R: Resource>(&self, name: &str) -> Result<R> {
-        let mut route = ResourceRoute::new(R::api(), R::kind().plural, name);
+    pub(crate) fn get<R: Resource>(&self, name: &str) -> Result<R> {
+        let mut route = ResourceRoute::new(R::api(), R::PLURAL, name);
         if let Some(ns) = self.get_ns::<R>() {
             route.namespace(ns);
         }
@@ -307,7 +735,7 @@ impl Kubernetes {
     }
 
     fn fetch_pod_async<R: Resource>(&self, name: &str) -> Result<()> {
-        let mut route = ResourceRoute::new(R::api(), R::kind().plural, name);
+        let mut route = ResourceRoute::new(R::api(), R::PLURAL, name);
         if let Some(ns) = self.get_ns::<R>() {
             route.namespace(ns);
         }
@@ -318,7 +746,7 @@ impl Kubernetes {
     }
 
     fn fetch_pod_future<R: Resource>(&self, name: &str) -> Result<RequestBuilder> {
-        let mut route = ResourceRoute::new(R::api(), R::kind().plural, name);
+        let mut route = ResourceRoute::new(R::api(), R::PLURAL, name);
         if let Some(ns) = self.get_ns::<R>() {
             route.namespace(ns);
         }
@@ -329,53 +757,300 @@ impl Kubernetes {
     }
 
     fn fetch_container_async<R: Resource>(&self, podname: &str, container: &str) -> Result<()> {
-        let mut route = ResourceRoute::new(R::api(), R::kind().plural, podname);
+        let mut route = ResourceRoute::new(R::api(), R::PLURAL, podname);
         if let Some(ns) = self.get_ns::<R>() {
             route.namespace(ns);
         }
 
         route.logs();
-        route.subresource(container);
+        route.container(container);
 
         self.low_level.get_async(&route)
     }
 
     fn fetch_container_future<R: Resource>(&self, podname: &str, container: &str) -> Result<RequestBuilder> {
-        let mut route = ResourceRoute::new(R::api(), R::kind().plural, podname);
+        let mut route = ResourceRoute::new(R::api(), R::PLURAL, podname);
         if let Some(ns) = self.get_ns::<R>() {
             route.namespace(ns);
         }
 
         route.logs();
-        route.subresource(container);
+        route.container(container);
 
         self.low_level.get_future(&route)
     }
 
     fn list<R: ListableResource>(&self, query: Option<&ListQuery>) -> Result<Vec<R>> {
-        let mut route = KindRoute::new(R::api(), R::kind().plural);
-        if let Some(ns) = self.get_ns::<R>() {
+        let mut route = KindRoute::new(R::api(), R::PLURAL);
+        if let Some(ns) = self.list_ns::<R>() {
             route.namespace(ns);
         }
         if let Some(query) = query {
             route.query(query.as_query_pairs());
         }
         let response: R::ListResponse = self.low_level.list(&route)?;
-        Ok(R::list_items(response))
+        Ok(R::typed_list_items(response))
+    }
+
+    pub(crate) fn list_with_version<R: ListableResource>(&self, query: Option<&ListQuery>) -> Result<(Vec<R>, Option<String>)> {
+        let page = self.list_page::<R>(query.unwrap_or(&ListQuery::default()))?;
+        Ok((page.items, page.resource_version))
+    }
+
+    pub(crate) fn list_page<R: ListableResource>(&self, query: &ListQuery) -> Result<ListPage<R>> {
+        let mut route = KindRoute::new(R::api(), R::PLURAL);
+        if let Some(ns) = self.list_ns::<R>() {
+            route.namespace(ns);
+        }
+        route.query(query.as_query_pairs());
+        ListPage::from_value(self.low_level.list(&route)?)
+    }
+
+    fn list_page_future<R: ListableResource>(&self, query: &ListQuery) -> impl Future<Item = ListPage<R>, Error = Error> {
+        let mut route = KindRoute::new(R::api(), R::PLURAL);
+        if let Some(ns) = self.list_ns::<R>() {
+            route.namespace(ns);
+        }
+        route.query(query.as_query_pairs());
+        self.low_level.list_future(&route).and_then(ListPage::from_value)
+    }
+
+    fn list_stream<R>(&self, query: Option<&ListQuery>) -> ListStream<R>
+    where R: ListableResource + Send + 'static
+    {
+        let kube = self.clone();
+        let query = paged_query(query);
+        // The state is the token of the next page to fetch, and `None` once done
+        let pages = stream::unfold(Some(None), move |continue_token: Option<Option<String>>| {
+            let query = match continue_token? {
+                Some(token) => query.continue_token(token),
+                None => query.clone(),
+            };
+            Some(kube.list_page_future::<R>(&query)
+                .map(|page| (stream::iter_ok(page.items), page.continue_token.map(Some))))
+        });
+        Box::new(pages.flatten())
+    }
+
+    pub(crate) fn watch<R: Resource>(&self, query: Option<&ListQuery>) -> Result<WatchEvents<R>> {
+        let mut route = KindRoute::new(R::api(), R::PLURAL);
+        if let Some(ns) = self.list_ns::<R>() {
+            route.namespace(ns);
+        }
+        let mut pairs = query.map(ListQuery::as_query_pairs).unwrap_or_default();
+        pairs.insert("watch", "true".to_owned());
+        route.query(pairs);
+        self.low_level.watch(&route)
+    }
+
+    fn get_modify_update<R, F>(&self, name: &str, mut modify: F) -> Result<R>
+    where R: Resource,
+          F: FnMut(&mut R),
+    {
+        let mut attempt = 1;
+        loop {
+            let mut resource = self.get::<R>(name)?;
+            modify(&mut resource);
+            match self.update(&resource) {
+                Err(ref e) if e.is_conflict() && attempt < MAX_CONFLICT_RETRIES => attempt += 1,
+                result => return result,
+            }
+        }
+    }
+
+    fn patch<R: Resource>(&self, name: &str, patch: &Patch) -> Result<R> {
+        let mut route = ResourceRoute::new(R::api(), R::PLURAL, name);
+        if let Some(ns) = self.get_ns::<R>() {
+            route.namespace(ns);
+        }
+        self.low_level.patch(&route, patch)
+    }
+
+    fn server_side_apply_resource<R: Resource>(&self, resource: &R, params: &ApplyParams) -> Result<R> {
+        let name = resource.metadata().name.as_ref()
+            .chain_err(|| "must set metadata.name to apply kubernetes resource")?;
+        let mut body = serde_json::to_value(resource)?;
+        // Unset fields would otherwise be applied as owned by this manager
+        strip_nulls(&mut body);
+        if let Value::Object(ref mut fields) = body {
+            fields.insert("apiVersion".to_owned(), Value::String(R::api_version()));
+            fields.insert("kind".to_owned(), Value::String(R::KIND.to_owned()));
+        }
+
+        let mut route = ResourceRoute::new(R::api(), R::PLURAL, name);
+        if let Some(ns) = self.get_ns::<R>() {
+            route.namespace(ns);
+        }
+        route.query(params.as_query_pairs());
+        self.low_level.patch(&route, &Patch::Apply(body))
     }
 
     fn delete<R: Resource>(&self, name: &str) -> Result<()> {
-        let mut route = ResourceRoute::new(R::api(), R::kind().plural, name);
+        let mut route = ResourceRoute::new(R::api(), R::PLURAL, name);
         if let Some(ns) = self.get_ns::<R>() {
             route.namespace(ns);
         }
         self.low_level.delete(&route)
     }
 
-    fn get_ns<'a, R: Resource>(&'a self) -> Option<&'a str> {
+    fn get_scale<R: Scalable>(&self, name: &str) -> Result<Scale> {
+        let mut route = ResourceRoute::new(R::api(), R::PLURAL, name);
+        if let Some(ns) = self.get_ns::<R>() {
+            route.namespace(ns);
+        }
+        route.subresource("scale");
+        self.low_level.get(&route)
+    }
+
+    fn scale<R: Scalable>(&self, name: &str, count: u32) -> Result<Scale> {
+        let mut route = ResourceRoute::new(R::api(), R::PLURAL, name);
+        if let Some(ns) = self.get_ns::<R>() {
+            route.namespace(ns);
+        }
+        route.subresource("scale");
+        let patch = Patch::builder().replace("/spec/replicas", Value::from(count)).build();
+        self.low_level.patch(&route, &patch)
+    }
+
+    fn get_status<R: HasStatus>(&self, name: &str) -> Result<R> {
+        let mut route = ResourceRoute::new(R::api(), R::PLURAL, name);
+        if let Some(ns) = self.get_ns::<R>() {
+            route.namespace(ns);
+        }
+        route.subresource("status");
+        self.low_level.get(&route)
+    }
+
+    fn replace_status<R: HasStatus>(&self, resource: &R) -> Result<R> {
+        let name = resource.metadata().name.as_ref()
+            .chain_err(|| "must set metadata.name to update kubernetes resource status")?;
+        let mut route = ResourceRoute::new(R::api(), R::PLURAL, name);
+        if let Some(ns) = self.object_ns(resource) {
+            route.namespace(ns);
+        }
+        route.subresource("status");
+        self.low_level.replace(&route, resource)
+    }
+
+    fn patch_status<R: HasStatus>(&self, name: &str, patch: &Patch) -> Result<R> {
+        let mut route = ResourceRoute::new(R::api(), R::PLURAL, name);
+        if let Some(ns) = self.get_ns::<R>() {
+            route.namespace(ns);
+        }
+        route.subresource("status");
+        self.low_level.patch(&route, patch)
+    }
+
+    fn delete_with<R: Resource>(&self, name: &str, params: &DeleteParams) -> Result<DeleteResponse<R>> {
+        let mut route = ResourceRoute::new(R::api(), R::PLURAL, name);
+        if let Some(ns) = self.get_ns::<R>() {
+            route.namespace(ns);
+        }
+        DeleteResponse::from_value(self.low_level.delete_with(&route, params)?)
+    }
+
+    fn delete_collection<R: ListableResource>(&self, query: &ListQuery, params: &DeleteParams) -> Result<DeleteResponse<Vec<R>>> {
+        let mut route = KindRoute::new(R::api(), R::PLURAL);
+        if let Some(ns) = self.get_ns::<R>() {
+            route.namespace(ns);
+        }
+        route.query(query.as_query_pairs());
+        DeleteResponse::from_list_value(self.low_level.delete_collection(&route, params)?)
+    }
+
+    // The namespace of list and watch routes, `None` when cluster-wide
+    pub(crate) fn list_ns<R: Resource>(&self) -> Option<&str> {
+        if self.all_namespaces {
+            None
+        } else {
+            self.get_ns::<R>()
+        }
+    }
+
+    pub(crate) fn get_ns<'a, R: Resource>(&'a self) -> Option<&'a str> {
+        if !R::NAMESPACED {
+            return None;
+        }
         match self.namespace {
             Some(ref ns) => Some(ns),
             None => R::default_namespace(),
         }
     }
+
+    // The namespace of manifests that don't set one, like `kubectl` does
+    fn manifest_ns(&self) -> &str {
+        self.namespace.as_deref().unwrap_or("default")
+    }
+
+    // The namespace of routes to an existing object: its own, or else the client's
+    pub(crate) fn object_ns<'a, R: Resource>(&'a self, resource: &'a R) -> Option<&'a str> {
+        if !R::NAMESPACED {
+            return None;
+        }
+        resource.metadata().namespace.as_deref().or_else(|| self.get_ns::<R>())
+    }
+}
+
+// Resolves the kinds of manifests, rediscovering once when a kind is unknown
+// (e.g. a CRD installed since discovery was cached)
+struct ManifestMapper<'a> {
+    kube: &'a Kubernetes,
+    mapper: RefCell<RestMapper>,
+    refreshed: Cell<bool>,
+}
+
+impl<'a> ManifestMapper<'a> {
+    fn new(kube: &'a Kubernetes) -> Result<ManifestMapper<'a>> {
+        Ok(ManifestMapper { kube, mapper: RefCell::new(kube.rest_mapper()?), refreshed: Cell::new(false) })
+    }
+
+    fn resource_for(&self, manifest: &Value) -> Result<ApiResource> {
+        let mini: MinimalResource = serde_json::from_value(manifest.clone())?;
+        if let Some(resource) = self.mapper.borrow().mapping(&mini.api_version, &mini.kind) {
+            return Ok(resource.clone());
+        }
+        if !self.refreshed.replace(true) {
+            *self.mapper.borrow_mut() = self.kube.refresh_discovery()?.rest_mapper();
+            return self.resource_for(manifest);
+        }
+        bail!("the server doesn't serve {} in {}", mini.kind, mini.api_version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake_server::FakeServer;
+    use k8s_api::api::apps::v1::DeploymentStatus;
+
+    #[test]
+    fn updates_go_to_the_namespace_of_the_object() {
+        let server = FakeServer::start();
+        let kube = server.kube().namespace("default");
+        let mut config_map = kube.namespace("prod").create(&ConfigMap::new("settings")).unwrap();
+        assert_eq!(config_map.metadata().namespace.as_deref(), Some("prod"));
+
+        config_map.insert("mode", "fast");
+        let mut config_map = kube.update(&config_map).unwrap();
+        let stored = server.object("/api/v1/namespaces/prod/configmaps/settings").unwrap();
+        assert_eq!(stored["data"]["mode"], "fast");
+        assert!(server.object("/api/v1/namespaces/default/configmaps/settings").is_none());
+
+        // Objects without a namespace are updated in the namespace of the client
+        config_map.metadata_mut().namespace = None;
+        assert!(kube.update(&config_map).unwrap_err().is_not_found());
+        kube.namespace("prod").update(&config_map).unwrap();
+    }
+
+    #[test]
+    fn statuses_are_replaced_in_the_namespace_of_the_object() {
+        let server = FakeServer::start();
+        let kube = server.kube().namespace("default");
+        let mut deployment = kube.namespace("prod").create(&Deployment::new("web")).unwrap();
+
+        deployment.status = Some(DeploymentStatus { ready_replicas: Some(3), ..Default::default() });
+        kube.deployments().replace_status(&deployment).unwrap();
+        let stored = server.object("/apis/apps/v1/namespaces/prod/deployments/web").unwrap();
+        assert_eq!(stored["status"]["readyReplicas"], 3);
+    }
 }
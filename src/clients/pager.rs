@@ -0,0 +1,258 @@
+use super::Kubernetes;
+use errors::*;
+use futures::Stream;
+use resources::{ListQuery, ListableResource};
+use serde_json::{self, Value};
+use std::collections::VecDeque;
+
+// The page size `kubectl` uses
+pub(crate) const DEFAULT_PAGE_SIZE: u32 = 500;
+
+/// A lazily fetched list of resources, see `KubeClient::list_stream`
+pub type ListStream<R> = Box<dyn Stream<Item = R, Error = Error> + Send>;
+
+/// A single page of a paginated list
+#[derive(Debug)]
+pub struct ListPage<R> {
+    pub items: Vec<R>,
+
+    /// The `resourceVersion` of the list, from which a watch can follow up
+    pub resource_version: Option<String>,
+
+    /// The token to fetch the next page with, `None` on the last page
+    pub continue_token: Option<String>,
+}
+
+impl<R: ListableResource> ListPage<R> {
+    pub(crate) fn from_value(response: Value) -> Result<ListPage<R>> {
+        let metadata = |field: &str| response.pointer(&format!("/metadata/{}", field))
+            .and_then(Value::as_str)
+            .filter(|value| !value.is_empty())
+            .map(str::to_owned);
+        let resource_version = metadata("resourceVersion");
+        let continue_token = metadata("continue");
+        let response: R::ListResponse = serde_json::from_value(response)?;
+        Ok(ListPage { items: R::typed_list_items(response), resource_version, continue_token })
+    }
+}
+
+/// Iterates over a list of resources, fetching it one page at a time
+///
+/// Pages hold at most `ListQuery::limit` resources (500 by default).
+///
+/// ## Examples
+///
+/// ```no_run
+/// # use qube::prelude::*;
+/// let kube = Kubernetes::load_conf("admin.conf")?;
+/// let pods = kube.pods().list_pages(None)?;
+/// let version = pods.resource_version().map(str::to_owned);
+/// for pod in pods {
+///     println!("{:?}", pod?.metadata.name);
+/// }
+/// # Ok::<(), qube::Error>(())
+/// ```
+pub struct ListPager<R> {
+    kube: Kubernetes,
+    query: ListQuery,
+    items: VecDeque<R>,
+    continue_token: Option<String>,
+    resource_version: Option<String>,
+    restart_on_expired: bool,
+    done: bool,
+}
+
+impl<R: ListableResource> ListPager<R> {
+    // Fetches the first page right away, so that `resource_version` is known
+    pub(crate) fn new(kube: &Kubernetes, query: Option<&ListQuery>) -> Result<ListPager<R>> {
+        let mut pager = ListPager {
+            kube: kube.clone(),
+            query: paged_query(query),
+            items: VecDeque::new(),
+            continue_token: None,
+            resource_version: None,
+            restart_on_expired: false,
+            done: false,
+        };
+        pager.fetch(None)?;
+        Ok(pager)
+    }
+
+    /// Starts over from the first page when the `continue` token expired,
+    /// instead of failing with a "gone" error (see `Error::is_gone`)
+    ///
+    /// Resources that were already returned are then returned again.
+    pub fn restart_on_expired(mut self) -> Self {
+        self.restart_on_expired = true;
+        self
+    }
+
+    /// The `resourceVersion` of the list, from which a watch can follow up
+    pub fn resource_version(&self) -> Option<&str> {
+        self.resource_version.as_deref()
+    }
+
+    fn fetch(&mut self, continue_token: Option<String>) -> Result<()> {
+        let query = match continue_token {
+            Some(token) => self.query.continue_token(token),
+            None => self.query.clone(),
+        };
+        let page = self.kube.list_page::<R>(&query)?;
+        self.items.extend(page.items);
+        self.resource_version = page.resource_version;
+        self.done = page.continue_token.is_none();
+        self.continue_token = page.continue_token;
+        Ok(())
+    }
+}
+
+impl<R: ListableResource> Iterator for ListPager<R> {
+    type Item = Result<R>;
+
+    fn next(&mut self) -> Option<Result<R>> {
+        loop {
+            if let Some(item) = self.items.pop_front() {
+                return Some(Ok(item));
+            }
+            if self.done {
+                return None;
+            }
+            let continue_token = self.continue_token.take();
+            let result = match self.fetch(continue_token) {
+                Err(ref e) if e.is_gone() && self.restart_on_expired => self.fetch(None),
+                result => result,
+            };
+            if let Err(e) = result {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+// Lists are paginated by the server only when they have a limit
+pub(crate) fn paged_query(query: Option<&ListQuery>) -> ListQuery {
+    let query = query.cloned().unwrap_or_default();
+    if query.has_limit() {
+        query
+    } else {
+        query.limit(DEFAULT_PAGE_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake_server::{FakeServer, Request};
+    use prelude::*;
+    use resources::Pod;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn pod_list(continue_token: &str) -> Value {
+        pods_page(&["web-0"], continue_token)
+    }
+
+    fn pods_page(names: &[&str], continue_token: &str) -> Value {
+        let items: Vec<Value> = names.iter()
+            .map(|name| json!({ "metadata": { "name": name }, "spec": { "containers": [] } }))
+            .collect();
+        json!({
+            "apiVersion": "v1",
+            "kind": "PodList",
+            "metadata": { "resourceVersion": "1042", "continue": continue_token },
+            "items": items
+        })
+    }
+
+    // Serves the pods web-0 to web-3 in three pages, and expires the first
+    // continue token once when `expire` is set
+    fn serve_pages(server: &FakeServer, expire: bool) {
+        let expired = AtomicBool::new(!expire);
+        server.on(move |request: &Request| {
+            let page = match request.query.get("continue").map(String::as_str) {
+                None => pods_page(&["web-0", "web-1"], "page-2"),
+                Some("page-2") if !expired.swap(true, Ordering::SeqCst) => return Some((410, json!({
+                    "kind": "Status",
+                    "status": "Failure",
+                    "reason": "Expired",
+                    "message": "The provided continue parameter is too old",
+                    "code": 410,
+                }))),
+                Some("page-2") => pods_page(&["web-2"], "page-3"),
+                Some(_) => pods_page(&["web-3"], ""),
+            };
+            Some((200, page))
+        });
+    }
+
+    fn names(pods: Vec<Pod>) -> Vec<String> {
+        pods.into_iter().map(|pod| pod.metadata.name.unwrap()).collect()
+    }
+
+    fn continue_tokens(server: &FakeServer) -> Vec<Option<String>> {
+        server.requests().into_iter().map(|request| request.query.get("continue").cloned()).collect()
+    }
+
+    #[test]
+    fn pages_carry_the_continue_token() {
+        let page = ListPage::<Pod>::from_value(pod_list("eyJ2IjoibWV0YS5rOHMuaW8vdjEifQ")).unwrap();
+        assert_eq!(page.continue_token.as_deref(), Some("eyJ2IjoibWV0YS5rOHMuaW8vdjEifQ"));
+        assert_eq!(page.resource_version.as_deref(), Some("1042"));
+        assert_eq!(page.items.len(), 1);
+    }
+
+    #[test]
+    fn the_last_page_has_an_empty_continue_token() {
+        let page = ListPage::<Pod>::from_value(pod_list("")).unwrap();
+        assert_eq!(page.continue_token, None);
+    }
+
+    #[test]
+    fn pagers_follow_the_continue_tokens() {
+        let server = FakeServer::start();
+        serve_pages(&server, false);
+        let pager = server.kube().pods().list_pages(None).unwrap();
+        assert_eq!(pager.resource_version(), Some("1042"));
+
+        let pods: Vec<Pod> = pager.collect::<Result<_>>().unwrap();
+        assert_eq!(names(pods), vec!["web-0", "web-1", "web-2", "web-3"]);
+        assert_eq!(continue_tokens(&server), vec![None, Some("page-2".to_owned()), Some("page-3".to_owned())]);
+        assert!(server.requests().iter().all(|request| request.query.get("limit").map(String::as_str) == Some("500")));
+    }
+
+    #[test]
+    fn expired_continue_tokens_fail_the_pager() {
+        let server = FakeServer::start();
+        serve_pages(&server, true);
+        let mut pager = server.kube().pods().list_pages(None).unwrap();
+
+        assert!(pager.next().unwrap().is_ok());
+        assert!(pager.next().unwrap().is_ok());
+        assert!(pager.next().unwrap().unwrap_err().is_gone());
+        assert!(pager.next().is_none());
+    }
+
+    #[test]
+    fn pagers_can_restart_on_expired_continue_tokens() {
+        let server = FakeServer::start();
+        serve_pages(&server, true);
+        let pager = server.kube().pods().list_pages(None).unwrap().restart_on_expired();
+
+        let pods: Vec<Pod> = pager.collect::<Result<_>>().unwrap();
+        assert_eq!(names(pods), vec!["web-0", "web-1", "web-0", "web-1", "web-2", "web-3"]);
+        assert_eq!(continue_tokens(&server), vec![
+            None,
+            Some("page-2".to_owned()),
+            None,
+            Some("page-2".to_owned()),
+            Some("page-3".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn queries_are_paged_by_default() {
+        assert!(paged_query(None).has_limit());
+        assert!(paged_query(Some(&ListQuery::default().limit(10))).has_limit());
+    }
+}
@@ -0,0 +1,610 @@
+use errors::*;
+use serde_json::{self, Map, Value};
+use std::collections::BTreeMap;
+
+/// The annotation in which `Kubernetes::apply` stores the last applied manifest,
+/// shared with `kubectl apply`
+pub const LAST_APPLIED_ANNOTATION: &str = "kubectl.kubernetes.io/last-applied-configuration";
+
+/// A partial update of a resource, in one of the formats the Kubernetes API accepts
+///
+/// ## Examples
+///
+/// ```no_run
+/// # use qube::prelude::*;
+/// # use qube::clients::{Patch, PatchOperation};
+/// # #[macro_use] extern crate serde_json;
+/// let kube = Kubernetes::load_conf("admin.conf")?;
+///
+/// let patch = Patch::Merge(json!({ "spec": { "replicas": 3 } }));
+/// kube.deployments().patch("web-server", &patch)?;
+///
+/// let patch = Patch::Json(vec![PatchOperation::replace("/spec/replicas", json!(5))]);
+/// kube.deployments().patch("web-server", &patch)?;
+/// # Ok::<(), qube::Error>(())
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum Patch {
+    /// A JSON merge patch (RFC 7386): objects are merged, and `null` removes a field
+    Merge(Value),
+
+    /// A JSON patch (RFC 6902): a list of operations applied in order
+    Json(Vec<PatchOperation>),
+
+    /// A strategic merge patch: like `Merge`, but lists of built-in kinds are merged by key
+    /// (e.g. containers by name) instead of being replaced
+    Strategic(Value),
+
+    /// A server-side apply: the fully specified intent of a field manager, which the server
+    /// merges with the fields owned by other managers (see `ApplyParams`)
+    Apply(Value),
+}
+
+impl Patch {
+    /// Starts building a merge patch
+    pub fn builder() -> PatchBuilder {
+        PatchBuilder::new()
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match *self {
+            Patch::Merge(_) => "application/merge-patch+json",
+            Patch::Json(_) => "application/json-patch+json",
+            Patch::Strategic(_) => "application/strategic-merge-patch+json",
+            // JSON is a subset of YAML, so the body is sent as JSON regardless
+            Patch::Apply(_) => "application/apply-patch+yaml",
+        }
+    }
+
+    /// Computes the merge patch turning `current` into `modified`, like `kubectl apply`
+    /// does for custom resources
+    ///
+    /// Fields set in `modified` are added or replaced, while fields of `original`
+    /// (the last applied configuration) that are no longer in `modified` are removed.
+    /// Other fields of `current`, e.g. set by controllers, are left alone.
+    ///
+    /// Lists are replaced as a whole whenever they differ from `current`, including when
+    /// the server filled in defaults of their items. Prefer `three_way_strategic` for
+    /// built-in kinds.
+    pub fn three_way_merge(original: Option<&Value>, modified: &Value, current: &Value) -> Patch {
+        Patch::Merge(three_way(original, modified, current, ListStrategy::Replace))
+    }
+
+    /// Computes the strategic merge patch turning `current` into `modified`, like `kubectl apply`
+    /// does for built-in kinds
+    ///
+    /// Like `three_way_merge`, except for the lists that built-in kinds merge by key,
+    /// e.g. containers and env vars by name, or volume mounts by mount path: their items
+    /// are merged one by one, leaving the fields and items that only `current` has alone.
+    /// Only built-in kinds accept strategic merge patches.
+    pub fn three_way_strategic(original: Option<&Value>, modified: &Value, current: &Value) -> Patch {
+        Patch::Strategic(three_way(original, modified, current, ListStrategy::MergeByKey))
+    }
+
+    /// Indicates whether applying this patch changes nothing
+    pub fn is_empty(&self) -> bool {
+        match *self {
+            Patch::Merge(ref patch) | Patch::Strategic(ref patch) => patch.as_object().is_some_and(Map::is_empty),
+            Patch::Json(ref operations) => operations.is_empty(),
+            Patch::Apply(_) => false,
+        }
+    }
+
+    pub(crate) fn to_body(&self) -> Result<Vec<u8>> {
+        let body = match *self {
+            Patch::Merge(ref patch) | Patch::Strategic(ref patch) | Patch::Apply(ref patch) => serde_json::to_vec(patch)?,
+            Patch::Json(ref operations) => serde_json::to_vec(operations)?,
+        };
+        Ok(body)
+    }
+}
+
+/// Parameters of a server-side apply
+///
+/// The server records which fields each field manager set. Applying a field that another
+/// manager owns with a different value fails with an `ErrorKind::ApplyConflict`,
+/// unless `force` is set, in which case the ownership of the field is taken over.
+///
+/// ## Examples
+///
+/// ```no_run
+/// # use qube::prelude::*;
+/// # use qube::clients::ApplyParams;
+/// let kube = Kubernetes::load_conf("admin.conf")?;
+/// let mut deployment = kube.deployments().get("web-server")?;
+/// deployment.spec.replicas = Some(3);
+/// let params = ApplyParams::new("my-controller").force();
+/// let response = kube.deployments().apply(&deployment, &params)?;
+/// # Ok::<(), qube::Error>(())
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApplyParams {
+    /// The name of the actor applying the changes, e.g. `my-controller`
+    pub field_manager: String,
+
+    /// Whether to take over fields owned by other managers instead of failing
+    pub force: bool,
+}
+
+impl ApplyParams {
+    pub fn new(field_manager: &str) -> ApplyParams {
+        ApplyParams { field_manager: field_manager.to_owned(), force: false }
+    }
+
+    /// Takes over the fields owned by other managers instead of failing with a conflict
+    pub fn force(mut self) -> ApplyParams {
+        self.force = true;
+        self
+    }
+
+    pub(crate) fn as_query_pairs(&self) -> BTreeMap<&str, String> {
+        let mut pairs = BTreeMap::new();
+        pairs.insert("fieldManager", self.field_manager.clone());
+        if self.force {
+            pairs.insert("force", "true".to_owned());
+        }
+        pairs
+    }
+}
+
+/// A single operation of a JSON patch (RFC 6902)
+///
+/// Paths are JSON pointers, e.g. `/metadata/labels/app`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+impl PatchOperation {
+    pub fn add(path: &str, value: Value) -> PatchOperation {
+        PatchOperation::Add { path: path.to_owned(), value }
+    }
+
+    pub fn remove(path: &str) -> PatchOperation {
+        PatchOperation::Remove { path: path.to_owned() }
+    }
+
+    pub fn replace(path: &str, value: Value) -> PatchOperation {
+        PatchOperation::Replace { path: path.to_owned(), value }
+    }
+
+    pub fn move_from(from: &str, path: &str) -> PatchOperation {
+        PatchOperation::Move { from: from.to_owned(), path: path.to_owned() }
+    }
+
+    pub fn copy_from(from: &str, path: &str) -> PatchOperation {
+        PatchOperation::Copy { from: from.to_owned(), path: path.to_owned() }
+    }
+
+    pub fn test(path: &str, value: Value) -> PatchOperation {
+        PatchOperation::Test { path: path.to_owned(), value }
+    }
+}
+
+/// Builds a merge patch out of common operations
+///
+/// ## Examples
+///
+/// ```no_run
+/// # use qube::prelude::*;
+/// # use qube::clients::Patch;
+/// # #[macro_use] extern crate serde_json;
+/// let kube = Kubernetes::load_conf("admin.conf")?;
+/// let patch = Patch::builder()
+///     .label("tier", "frontend")
+///     .remove_annotation("deprecated-by")
+///     .replace("/spec/replicas", json!(3))
+///     .build();
+/// kube.deployments().patch("web-server", &patch)?;
+/// # Ok::<(), qube::Error>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PatchBuilder {
+    patch: Map<String, Value>,
+}
+
+impl PatchBuilder {
+    pub fn new() -> PatchBuilder {
+        PatchBuilder::default()
+    }
+
+    /// Adds or replaces a label
+    pub fn label(self, key: &str, value: &str) -> PatchBuilder {
+        self.set(&["metadata", "labels", key], Value::String(value.to_owned()))
+    }
+
+    /// Removes a label
+    pub fn remove_label(self, key: &str) -> PatchBuilder {
+        self.set(&["metadata", "labels", key], Value::Null)
+    }
+
+    /// Adds or replaces an annotation
+    pub fn annotation(self, key: &str, value: &str) -> PatchBuilder {
+        self.set(&["metadata", "annotations", key], Value::String(value.to_owned()))
+    }
+
+    /// Removes an annotation
+    pub fn remove_annotation(self, key: &str) -> PatchBuilder {
+        self.set(&["metadata", "annotations", key], Value::Null)
+    }
+
+    /// Replaces the field at a JSON pointer, e.g. `/spec/replicas`
+    ///
+    /// Lists are replaced as a whole, as merge patches can't address list items.
+    pub fn replace(self, path: &str, value: Value) -> PatchBuilder {
+        let segments = pointer_segments(path);
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+        self.set(&segments, value)
+    }
+
+    /// Removes the field at a JSON pointer, e.g. `/spec/template/spec/nodeSelector`
+    pub fn remove(self, path: &str) -> PatchBuilder {
+        self.replace(path, Value::Null)
+    }
+
+    pub fn build(self) -> Patch {
+        Patch::Merge(Value::Object(self.patch))
+    }
+
+    fn set(mut self, path: &[&str], value: Value) -> PatchBuilder {
+        if let Some((last, parents)) = path.split_last() {
+            let mut object = &mut self.patch;
+            for segment in parents {
+                let child = object.entry(segment.to_string())
+                    .or_insert_with(|| Value::Object(Map::new()));
+                if !child.is_object() {
+                    *child = Value::Object(Map::new());
+                }
+                object = match *child {
+                    Value::Object(ref mut map) => map,
+                    _ => unreachable!(),
+                };
+            }
+            object.insert(last.to_string(), value);
+        }
+        self
+    }
+}
+
+// How a three-way merge handles lists
+#[derive(Clone, Copy, PartialEq)]
+enum ListStrategy {
+    /// Lists are replaced as a whole, as in JSON merge patches
+    Replace,
+
+    /// Lists with a merge key are merged item by item, as in strategic merge patches
+    MergeByKey,
+}
+
+fn three_way(original: Option<&Value>, modified: &Value, current: &Value, lists: ListStrategy) -> Value {
+    let empty = Map::new();
+    let original = original.and_then(Value::as_object).unwrap_or(&empty);
+    match (modified.as_object(), current.as_object()) {
+        (Some(modified), Some(current)) => Value::Object(three_way_objects(original, modified, current, lists, "")),
+        _ => modified.clone(),
+    }
+}
+
+// `parent` is the field holding the objects, which tells apart lists of the same name
+fn three_way_objects(
+    original: &Map<String, Value>,
+    modified: &Map<String, Value>,
+    current: &Map<String, Value>,
+    lists: ListStrategy,
+    parent: &str,
+) -> Map<String, Value> {
+    let mut patch = Map::new();
+    for (key, value) in modified {
+        match (value, current.get(key)) {
+            (Value::Object(value), Some(Value::Object(live))) => {
+                let empty = Map::new();
+                let original = original.get(key).and_then(Value::as_object).unwrap_or(&empty);
+                let nested = three_way_objects(original, value, live, lists, key);
+                if !nested.is_empty() {
+                    patch.insert(key.clone(), Value::Object(nested));
+                }
+            }
+            (Value::Array(items), Some(Value::Array(live))) if lists == ListStrategy::MergeByKey => {
+                let original_items = original.get(key).and_then(Value::as_array).map(Vec::as_slice).unwrap_or(&[]);
+                let keyed = merge_key(key, parent)
+                    .and_then(|merge_key| three_way_keyed_list(merge_key, key, original_items, items, live).map(|patch| (merge_key, patch)));
+                match keyed {
+                    Some((_, ref list_patch)) if list_patch.is_empty() => {}
+                    Some((merge_key, list_patch)) => {
+                        // Keeps the items in the order of the manifest
+                        let order = items.iter().map(|item| key_only(merge_key, item[merge_key].clone())).collect();
+                        patch.insert(key.clone(), Value::Array(list_patch));
+                        patch.insert(format!("$setElementOrder/{}", key), Value::Array(order));
+                    }
+                    // Without a merge key, the list is replaced like in a merge patch
+                    None => if items != live {
+                        patch.insert(key.clone(), value.clone());
+                    },
+                }
+            }
+            (value, live) if live != Some(value) => {
+                patch.insert(key.clone(), value.clone());
+            }
+            _ => {}
+        }
+    }
+    for key in original.keys() {
+        if !modified.contains_key(key) && current.contains_key(key) {
+            patch.insert(key.clone(), Value::Null);
+        }
+    }
+    patch
+}
+
+// Merges the items of a list by the value of their `merge_key` field, returning the patched
+// items, or `None` if an item lacks the key, in which case the list can't be merged by key
+fn three_way_keyed_list(
+    merge_key: &str,
+    field: &str,
+    original: &[Value],
+    modified: &[Value],
+    current: &[Value],
+) -> Option<Vec<Value>> {
+    let key_of = |item: &Value| item.get(merge_key).cloned();
+    let find = |items: &[Value], key: &Value| items.iter().find(|item| item.get(merge_key) == Some(key)).cloned();
+    let modified_keys = modified.iter().map(key_of).collect::<Option<Vec<Value>>>()?;
+
+    let mut patch = Vec::new();
+    for (item, key) in modified.iter().zip(&modified_keys) {
+        match (item.as_object(), find(current, key)) {
+            (Some(item), Some(Value::Object(live))) => {
+                let empty = Map::new();
+                let original_item = find(original, key);
+                let original_item = original_item.as_ref().and_then(Value::as_object).unwrap_or(&empty);
+                let mut nested = three_way_objects(original_item, item, &live, ListStrategy::MergeByKey, field);
+                if !nested.is_empty() {
+                    nested.insert(merge_key.to_owned(), key.clone());
+                    patch.push(Value::Object(nested));
+                }
+            }
+            _ => patch.push(item.clone()),
+        }
+    }
+    // Items applied before, but no longer in the manifest
+    for key in original.iter().filter_map(key_of) {
+        if !modified_keys.contains(&key) && find(current, &key).is_some() {
+            let mut delete = key_only(merge_key, key);
+            delete["$patch"] = Value::from("delete");
+            patch.push(delete);
+        }
+    }
+    Some(patch)
+}
+
+// An object only holding a merge key, e.g. `{"name": "nginx"}`
+fn key_only(merge_key: &str, key: Value) -> Value {
+    let mut item = Map::new();
+    item.insert(merge_key.to_owned(), key);
+    Value::Object(item)
+}
+
+// The field identifying the items of a list of a built-in kind, for the lists
+// that are merged by key (`patchMergeKey` in the Kubernetes API)
+fn merge_key(field: &str, parent: &str) -> Option<&'static str> {
+    let in_container = parent == "containers" || parent == "initContainers" || parent == "ephemeralContainers";
+    match field {
+        "containers" | "initContainers" | "ephemeralContainers" | "volumes" | "imagePullSecrets" => Some("name"),
+        "env" if in_container => Some("name"),
+        "volumeMounts" if in_container => Some("mountPath"),
+        "volumeDevices" if in_container => Some("devicePath"),
+        "ports" if in_container => Some("containerPort"),
+        // The ports of a `Service`
+        "ports" if parent == "spec" => Some("port"),
+        "hostAliases" => Some("ip"),
+        "ownerReferences" => Some("uid"),
+        _ => None,
+    }
+}
+
+// Removes the null fields of objects, at any depth
+pub(crate) fn strip_nulls(value: &mut Value) {
+    match *value {
+        Value::Object(ref mut fields) => {
+            fields.retain(|_, field| !field.is_null());
+            fields.values_mut().for_each(strip_nulls);
+        }
+        Value::Array(ref mut items) => items.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+// Splits a JSON pointer into its unescaped segments
+fn pointer_segments(path: &str) -> Vec<String> {
+    path.trim_start_matches('/')
+        .split('/')
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn builder_nests_fields_into_a_merge_patch() {
+        let patch = Patch::builder()
+            .label("tier", "frontend")
+            .remove_label("legacy")
+            .remove_annotation("deprecated-by")
+            .replace("/spec/replicas", json!(3))
+            .remove("/spec/template/spec/nodeSelector")
+            .build();
+        assert_eq!(patch, Patch::Merge(json!({
+            "metadata": {
+                "labels": { "tier": "frontend", "legacy": null },
+                "annotations": { "deprecated-by": null },
+            },
+            "spec": {
+                "replicas": 3,
+                "template": { "spec": { "nodeSelector": null } },
+            },
+        })));
+    }
+
+    #[test]
+    fn builder_unescapes_json_pointers() {
+        let patch = Patch::builder()
+            .replace("/metadata/annotations/example.com~1owner", json!("team-a"))
+            .replace("/metadata/annotations/a~0b", json!("c"))
+            .build();
+        assert_eq!(patch, Patch::Merge(json!({
+            "metadata": { "annotations": { "example.com/owner": "team-a", "a~b": "c" } },
+        })));
+    }
+
+    #[test]
+    fn later_paths_replace_scalars_on_their_way() {
+        let patch = Patch::builder()
+            .replace("/spec", json!(null))
+            .replace("/spec/replicas", json!(1))
+            .build();
+        assert_eq!(patch, Patch::Merge(json!({ "spec": { "replicas": 1 } })));
+    }
+
+    #[test]
+    fn json_patches_serialize_as_operations() {
+        let patch = Patch::Json(vec![
+            PatchOperation::test("/metadata/resourceVersion", json!("42")),
+            PatchOperation::replace("/spec/replicas", json!(5)),
+            PatchOperation::move_from("/metadata/labels/old", "/metadata/labels/new"),
+        ]);
+        let body: Value = serde_json::from_slice(&patch.to_body().unwrap()).unwrap();
+        assert_eq!(body, json!([
+            { "op": "test", "path": "/metadata/resourceVersion", "value": "42" },
+            { "op": "replace", "path": "/spec/replicas", "value": 5 },
+            { "op": "move", "from": "/metadata/labels/old", "path": "/metadata/labels/new" },
+        ]));
+        assert_eq!(patch.content_type(), "application/json-patch+json");
+    }
+
+    fn deployment(containers: Value) -> Value {
+        json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": { "name": "web" },
+            "spec": { "template": { "spec": { "containers": containers } } },
+        })
+    }
+
+    #[test]
+    fn three_way_merges_remove_fields_no_longer_applied() {
+        let original = json!({ "metadata": { "labels": { "app": "web", "tier": "frontend" } }, "spec": { "replicas": 2 } });
+        let modified = json!({ "metadata": { "labels": { "app": "web" } }, "spec": { "replicas": 3 } });
+        let current = json!({
+            "metadata": { "labels": { "app": "web", "tier": "frontend", "added-by": "controller" } },
+            "spec": { "replicas": 2, "paused": false },
+        });
+        let patch = Patch::three_way_merge(Some(&original), &modified, &current);
+        assert_eq!(patch, Patch::Merge(json!({
+            "metadata": { "labels": { "tier": null } },
+            "spec": { "replicas": 3 },
+        })));
+    }
+
+    #[test]
+    fn three_way_merges_replace_lists() {
+        let modified = deployment(json!([{ "name": "app", "image": "nginx:1.25" }]));
+        let current = deployment(json!([{ "name": "app", "image": "nginx:1.25", "imagePullPolicy": "IfNotPresent" }]));
+        let patch = Patch::three_way_merge(Some(&modified), &modified, &current);
+        assert_eq!(patch, Patch::Merge(json!({
+            "spec": { "template": { "spec": { "containers": [{ "name": "app", "image": "nginx:1.25" }] } } },
+        })));
+    }
+
+    #[test]
+    fn strategic_merges_ignore_server_defaults() {
+        let modified = deployment(json!([{ "name": "app", "image": "nginx:1.25", "ports": [{ "containerPort": 80 }] }]));
+        let current = deployment(json!([{
+            "name": "app",
+            "image": "nginx:1.25",
+            "imagePullPolicy": "IfNotPresent",
+            "ports": [{ "containerPort": 80, "protocol": "TCP" }],
+            "terminationMessagePath": "/dev/termination-log",
+        }]));
+        assert!(Patch::three_way_strategic(Some(&modified), &modified, &current).is_empty());
+    }
+
+    #[test]
+    fn strategic_merges_patch_list_items_by_key() {
+        let original = deployment(json!([
+            { "name": "app", "image": "nginx:1.24", "env": [{ "name": "MODE", "value": "slow" }, { "name": "DEBUG", "value": "1" }] },
+            { "name": "sidecar", "image": "envoy" },
+        ]));
+        let modified = deployment(json!([
+            { "name": "app", "image": "nginx:1.25", "env": [{ "name": "MODE", "value": "slow" }] },
+            { "name": "metrics", "image": "exporter" },
+        ]));
+        let current = deployment(json!([
+            {
+                "name": "app",
+                "image": "nginx:1.24",
+                "env": [{ "name": "MODE", "value": "slow" }, { "name": "DEBUG", "value": "1" }, { "name": "INJECTED", "value": "x" }],
+            },
+            { "name": "sidecar", "image": "envoy" },
+            { "name": "injected", "image": "proxy" },
+        ]));
+        let patch = Patch::three_way_strategic(Some(&original), &modified, &current);
+        assert_eq!(patch, Patch::Strategic(json!({ "spec": { "template": { "spec": {
+            "containers": [
+                {
+                    "name": "app",
+                    "image": "nginx:1.25",
+                    "env": [{ "name": "DEBUG", "$patch": "delete" }],
+                    "$setElementOrder/env": [{ "name": "MODE" }],
+                },
+                { "name": "metrics", "image": "exporter" },
+                { "name": "sidecar", "$patch": "delete" },
+            ],
+            "$setElementOrder/containers": [{ "name": "app" }, { "name": "metrics" }],
+        } } } })));
+    }
+
+    #[test]
+    fn strategic_merges_replace_lists_without_merge_key() {
+        let original = json!({ "spec": { "tolerations": [{ "key": "a", "operator": "Exists" }], "ports": [{ "port": 80 }] } });
+        let modified = json!({ "spec": { "tolerations": [{ "key": "b", "operator": "Exists" }], "ports": [{ "port": 80 }] } });
+        let current = json!({ "spec": {
+            "tolerations": [{ "key": "a", "operator": "Exists" }],
+            "ports": [{ "port": 80, "protocol": "TCP", "targetPort": 80 }],
+        } });
+        let patch = Patch::three_way_strategic(Some(&original), &modified, &current);
+        assert_eq!(patch, Patch::Strategic(json!({ "spec": { "tolerations": [{ "key": "b", "operator": "Exists" }] } })));
+    }
+
+    #[test]
+    fn nulls_are_stripped_at_any_depth() {
+        let mut value = json!({
+            "metadata": { "name": "web", "labels": null },
+            "spec": {
+                "replicas": null,
+                "template": { "spec": { "containers": [{ "name": "app", "image": null, "args": [null] }] } },
+            },
+            "status": null,
+        });
+        strip_nulls(&mut value);
+        assert_eq!(value, json!({
+            "metadata": { "name": "web" },
+            "spec": { "template": { "spec": { "containers": [{ "name": "app", "args": [null] }] } } },
+        }));
+    }
+
+    #[test]
+    fn empty_patches() {
+        assert!(Patch::builder().build().is_empty());
+        assert!(Patch::Json(vec![]).is_empty());
+        assert!(!Patch::Merge(json!({ "spec": {} })).is_empty());
+        assert!(!Patch::Apply(json!({})).is_empty());
+    }
+}
@@ -2,8 +2,13 @@ use super::Kubernetes;
 use resources::*;
 use errors::*;
 use std::marker::PhantomData;
-use super::ResourceRoute;
+use super::{ApplyParams, DeleteParams, DeleteResponse, ListPager, ListStream, Patch, WatchEvents};
 use reqwest::async::RequestBuilder;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How often `wait_for_ready_replicas` and `ensure_crd` check the resource
+pub(super) const READY_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 pub struct KubeClient<R> {
     pub(super) kube: Kubernetes,
@@ -16,9 +21,79 @@ impl<R> KubeClient<R> {
         KubeClient { kube: self.kube.namespace(namespace), _marker: PhantomData }
     }
 
+    /// Get a kubernetes client that lists and watches resources across all namespaces
+    pub fn all_namespaces(&self) -> Self {
+        KubeClient { kube: self.kube.all_namespaces(), _marker: PhantomData }
+    }
+
     pub fn logs(&self) -> Self {
         KubeClient { kube: self.kube.logs(), _marker: PhantomData }
     }
+
+    /// Get a kubernetes client that only validates mutating calls, without persisting them
+    pub fn dry_run(&self) -> Self {
+        KubeClient { kube: self.kube.dry_run(), _marker: PhantomData }
+    }
+
+    /// Checks that the server serves this kind, see `Kubernetes::check_served`
+    pub fn check_served(&self) -> Result<()> where R: Resource {
+        self.kube.check_served::<R>()
+    }
+}
+
+impl<R: HasStatus> KubeClient<R> {
+    /// Gets the named resource through its `/status` subresource
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// let deployment = kube.deployments().get_status("web-server")?;
+    /// println!("{:?}", deployment.status);
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn get_status(&self, name: &str) -> Result<R> {
+        self.kube.get_status(name)
+    }
+
+    /// Replaces the status of the named resource, leaving the rest of it (e.g. `spec`) untouched
+    ///
+    /// Like `update`, this is conditional on `metadata.resourceVersion` when it is set,
+    /// and goes to the namespace of the resource when `metadata.namespace` is set.
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// let mut deployment = kube.deployments().get_status("web-server")?;
+    /// if let Some(ref mut status) = deployment.status {
+    ///     status.observed_generation = deployment.metadata.generation;
+    /// }
+    /// kube.deployments().replace_status(&deployment)?;
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn replace_status(&self, resource: &R) -> Result<R> {
+        self.kube.replace_status(resource)
+    }
+
+    /// Partially updates the status of the named resource
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// # use qube::clients::Patch;
+    /// # #[macro_use] extern crate serde_json;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// let patch = Patch::Merge(json!({ "status": { "observedGeneration": 2 } }));
+    /// kube.deployments().patch_status("web-server", &patch)?;
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn patch_status(&self, name: &str, patch: &Patch) -> Result<R> {
+        self.kube.patch_status(name, patch)
+    }
 }
 
 // impl KubeClient<Pod> {
@@ -41,27 +116,154 @@ impl<R> KubeClient<R> {
 //     }
 // }
 
-impl KubeClient<Deployment> {
-    /// Scale a deployment to a specific number of pods
+impl<R: ListableResource + Send + 'static> KubeClient<R> {
+    /// Lists resources of a particular type asynchronously, fetching them one page at a time
+    ///
+    /// This is the asynchronous counterpart of `list_pages`. An expired `continue` token
+    /// ends the stream with a "gone" error (see `Error::is_gone`).
     ///
     /// ## Examples
     ///
     /// ```no_run
-    /// # use kubeclient::prelude::*;
+    /// # extern crate futures;
+    /// # extern crate tokio;
+    /// # use qube::prelude::*;
+    /// # use futures::{Future, Stream};
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// let pods = kube.pods().list_stream(None)
+    ///     .for_each(|pod| Ok(println!("{:?}", pod.metadata.name)))
+    ///     .map_err(|e| eprintln!("{}", e));
+    /// tokio::run(pods);
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn list_stream(&self, query: Option<&ListQuery>) -> ListStream<R> {
+        self.kube.list_stream(query)
+    }
+}
+
+impl<R: Scalable> KubeClient<R> {
+    /// Gets the scale subresource of the named workload
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// let scale = kube.deployments().get_scale("web-server")?;
+    /// println!("{:?} replicas", scale.spec.replicas);
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn get_scale(&self, name: &str) -> Result<Scale> {
+        self.kube.get_scale::<R>(name)
+    }
+
+    /// Scale a workload to a specific number of pods
+    ///
+    /// This is similar to the `kubectl scale` CLI commands.
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// kube.deployments().scale("web-server", 7)?;
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn scale(&self, name: &str, count: u32) -> Result<Scale> {
+        self.kube.scale::<R>(name, count)
+    }
+
+    /// Waits until the named workload has at least `count` ready replicas
+    ///
+    /// Fails if that didn't happen within `timeout`.
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// # use std::time::Duration;
     /// let kube = Kubernetes::load_conf("admin.conf")?;
     /// kube.deployments().scale("web-server", 7)?;
+    /// kube.deployments().wait_for_ready_replicas("web-server", 7, Duration::from_secs(120))?;
+    /// # Ok::<(), qube::Error>(())
     /// ```
-    pub fn scale(&self, deployment_name: &str, count: u32) -> Result<Scale> {
-        let resource = format!("{}/scale", deployment_name);
-        let mut route = ResourceRoute::new(Deployment::api(), Deployment::kind().plural, &resource);
-        let ns = self.kube.get_ns::<Deployment>().expect("Namespace necessary for kubernetes scale operation");
-        route.namespace(ns);
+    pub fn wait_for_ready_replicas(&self, name: &str, count: u32, timeout: Duration) -> Result<R> {
+        let start = Instant::now();
+        loop {
+            let resource = self.kube.get::<R>(name)?;
+            if resource.ready_replicas() >= count as i32 {
+                return Ok(resource);
+            }
+            if start.elapsed() >= timeout {
+                bail!("{} {} has {} of {} replicas ready after {:?}", R::KIND, name, resource.ready_replicas(), count, timeout);
+            }
+            thread::sleep(READY_POLL_INTERVAL);
+        }
+    }
+}
 
-        let url = route.build(&self.kube.low_level.base_url)?;
+impl KubeClient<Event> {
+    /// Lists the events about a resource, oldest first
+    ///
+    /// This is similar to the events section of the `kubectl describe` CLI commands.
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// let pod = kube.pods().get("web-server-abcdefgh12345678")?;
+    /// let events = kube.events().for_object(&pod)?;
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn for_object<O: Resource>(&self, resource: &O) -> Result<Vec<Event>> {
+        let (kube, query) = self.object_query(resource);
+        let mut events = kube.list::<Event>(Some(&query))?;
+        events.sort_by_key(|event| {
+            event.last_timestamp.as_ref()
+                .or(event.first_timestamp.as_ref())
+                .or(event.metadata.creation_timestamp.as_ref())
+                .map(|time| time.0)
+        });
+        Ok(events)
+    }
 
-        let body = Scale::replicas(&ns, &deployment_name, count);
-        let resp = self.kube.low_level.http_put_json(url, &body)?;
-        Ok(resp)
+    /// Watches the events about a resource
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// let pod = kube.pods().get("web-server-abcdefgh12345678")?;
+    /// for event in kube.events().watch_for_object(&pod)? {
+    ///     println!("{:?}", event?);
+    /// }
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    pub fn watch_for_object<O: Resource>(&self, resource: &O) -> Result<WatchEvents<Event>> {
+        let (kube, query) = self.object_query(resource);
+        kube.watch::<Event>(Some(&query))
+    }
+
+    // Events live in the namespace of their object (cluster-scoped objects use the default namespace)
+    fn object_query<O: Resource>(&self, resource: &O) -> (Kubernetes, ListQuery) {
+        let object = resource.object_reference();
+        let mut selectors = vec![format!("involvedObject.kind={}", O::KIND)];
+        if let Some(ref name) = object.name {
+            selectors.push(format!("involvedObject.name={}", name));
+        }
+        if let Some(ref namespace) = object.namespace {
+            selectors.push(format!("involvedObject.namespace={}", namespace));
+        }
+        if let Some(ref uid) = object.uid {
+            selectors.push(format!("involvedObject.uid={}", uid));
+        }
+        let kube = match object.namespace {
+            Some(ref namespace) => self.kube.namespace(namespace),
+            None => self.kube.clone(),
+        };
+        (kube, ListQuery::default().field_selector(selectors.join(",")))
     }
 }
 
@@ -134,10 +336,89 @@ pub trait WriteClient {
     /// ```
     fn create(&self, resource: &Self::R) -> Result<Self::R>;
 
+    /// Replaces the named resource
+    ///
+    /// This is similar to the `kubectl replace` CLI commands.
+    ///
+    /// The update is conditional on `metadata.resourceVersion` when it is set:
+    /// if the resource changed since that version, the call fails with a conflict
+    /// (see `Error::is_conflict`).
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// let mut cfg_map = kube.config_maps().get("my-config-map")?;
+    /// cfg_map.insert("environment", "staging");
+    /// let response = kube.config_maps().update(&cfg_map)?;
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    fn update(&self, resource: &Self::R) -> Result<Self::R>;
+
+    /// Reads the named resource, modifies it and replaces it
+    ///
+    /// When someone else changed the resource in the meantime, the update fails with a
+    /// conflict; it is then read and modified again, up to 5 times in total.
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// kube.deployments().get_modify_update("web-server", |deployment| {
+    ///     deployment.spec.replicas = Some(3);
+    /// })?;
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    fn get_modify_update<F>(&self, name: &str, modify: F) -> Result<Self::R>
+    where F: FnMut(&mut Self::R);
+
+    /// Partially updates the named resource
+    ///
+    /// This is similar to the `kubectl patch` CLI commands.
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// # use qube::clients::Patch;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// let patch = Patch::builder().label("tier", "frontend").build();
+    /// let response = kube.deployments().patch("web-server", &patch)?;
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    fn patch(&self, name: &str, patch: &Patch) -> Result<Self::R>;
+
+    /// Applies a resource with a server-side apply, creating it if it doesn't exist
+    ///
+    /// This is similar to the `kubectl apply --server-side` CLI commands.
+    ///
+    /// `resource` should only set the fields `params.field_manager` cares about.
+    /// Changing fields owned by other managers fails with an `ErrorKind::ApplyConflict`
+    /// (see `Error::apply_conflicts`), unless `params.force` is set.
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// # use qube::clients::ApplyParams;
+    /// # use qube::resources::ConfigMap;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// let mut cfg_map = ConfigMap::new("stage-config");
+    /// cfg_map.insert("environment", "production");
+    /// let response = kube.config_maps().apply(&cfg_map, &ApplyParams::new("deployer"))?;
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    fn apply(&self, resource: &Self::R, params: &ApplyParams) -> Result<Self::R>;
+
     /// Deleteds the named resource
     ///
     /// This is similar to the `kubectl delete` CLI commands.
     ///
+    /// The response is dropped, so use `delete_with` to see what a `dry_run` client
+    /// would have deleted.
+    ///
     /// ## Examples
     ///
     /// ```no_run
@@ -146,6 +427,25 @@ pub trait WriteClient {
     /// kube.config_maps().delete("my-config-map")?;
     /// ```
     fn delete(&self, name: &str) -> Result<()>;
+
+    /// Deletes the named resource with options, e.g. a propagation policy
+    ///
+    /// This is similar to the `kubectl delete --cascade --grace-period` CLI commands.
+    ///
+    /// The response is the resource marked for deletion when the deletion is not complete yet
+    /// (e.g. pending finalizers), and the `Status` reported by the server otherwise.
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// # use qube::clients::{DeleteParams, PropagationPolicy};
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// let params = DeleteParams::default().propagation_policy(PropagationPolicy::Orphan);
+    /// kube.deployments().delete_with("web-server", &params)?;
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    fn delete_with(&self, name: &str, params: &DeleteParams) -> Result<DeleteResponse<Self::R>>;
 }
 
 pub trait ListClient {
@@ -165,6 +465,60 @@ pub trait ListClient {
     /// let cfg_maps = kube.config_maps().list("my-config-map", None)?;
     /// ```
     fn list(&self, query: Option<&ListQuery>) -> Result<Vec<Self::R>>;
+
+    /// Lists resources of a particular type, fetching them one page at a time
+    ///
+    /// Unlike `list`, the whole list is never held in memory, which matters for large clusters.
+    /// The page size is the `limit` of the `query`, 500 by default.
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// # use qube::resources::ListQuery;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// for pod in kube.pods().list_pages(Some(&ListQuery::default().limit(100)))? {
+    ///     println!("{:?}", pod?.metadata.name);
+    /// }
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    fn list_pages(&self, query: Option<&ListQuery>) -> Result<ListPager<Self::R>>;
+
+    /// Watches resources of a particular type for changes
+    ///
+    /// This is similar to the `kubectl get --watch` CLI commands.
+    ///
+    /// The returned iterator blocks until the next event arrives, and ends when the
+    ///   server closes the watch (see `ListQuery::timeout_seconds`).
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// for event in kube.pods().watch(None)? {
+    ///     println!("{:?}", event?);
+    /// }
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    fn watch(&self, query: Option<&ListQuery>) -> Result<WatchEvents<Self::R>>;
+
+    /// Deletes all the resources of a particular type matching `query`
+    ///
+    /// This is similar to the `kubectl delete --selector` CLI commands.
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// # use qube::prelude::*;
+    /// # use qube::clients::DeleteParams;
+    /// # use qube::resources::ListQuery;
+    /// let kube = Kubernetes::load_conf("admin.conf")?;
+    /// let query = ListQuery::default().label_selector("app=web-server");
+    /// kube.deployments().delete_collection(&query, &DeleteParams::default())?;
+    /// # Ok::<(), qube::Error>(())
+    /// ```
+    fn delete_collection(&self, query: &ListQuery, params: &DeleteParams) -> Result<DeleteResponse<Vec<Self::R>>>;
 }
 
 impl<R: Resource> ReadClient for KubeClient<R> {
@@ -197,6 +551,17 @@ impl<R: ListableResource> ListClient for KubeClient<R> {
         self.kube.list::<Self::R>(query)
     }
 
+    fn list_pages(&self, query: Option<&ListQuery>) -> Result<ListPager<Self::R>> {
+        ListPager::new(&self.kube, query)
+    }
+
+    fn watch(&self, query: Option<&ListQuery>) -> Result<WatchEvents<Self::R>> {
+        self.kube.watch::<Self::R>(query)
+    }
+
+    fn delete_collection(&self, query: &ListQuery, params: &DeleteParams) -> Result<DeleteResponse<Vec<Self::R>>> {
+        self.kube.delete_collection::<Self::R>(query, params)
+    }
 }
 
 impl<R: Resource> WriteClient for KubeClient<R> {
@@ -206,7 +571,29 @@ impl<R: Resource> WriteClient for KubeClient<R> {
         self.kube.create(resource)
     }
 
+    fn update(&self, resource: &Self::R) -> Result<Self::R> {
+        self.kube.update(resource)
+    }
+
+    fn get_modify_update<F>(&self, name: &str, modify: F) -> Result<Self::R>
+    where F: FnMut(&mut Self::R)
+    {
+        self.kube.get_modify_update(name, modify)
+    }
+
+    fn patch(&self, name: &str, patch: &Patch) -> Result<Self::R> {
+        self.kube.patch::<Self::R>(name, patch)
+    }
+
+    fn apply(&self, resource: &Self::R, params: &ApplyParams) -> Result<Self::R> {
+        self.kube.server_side_apply_resource(resource, params)
+    }
+
     fn delete(&self, name: &str) -> Result<()> {
         self.kube.delete::<Self::R>(name)
     }
+
+    fn delete_with(&self, name: &str, params: &DeleteParams) -> Result<DeleteResponse<Self::R>> {
+        self.kube.delete_with::<Self::R>(name, params)
+    }
 }
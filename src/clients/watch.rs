@@ -0,0 +1,30 @@
+use reqwest;
+use serde::de::DeserializeOwned;
+use serde_json::{Deserializer, StreamDeserializer};
+use serde_json::de::IoRead;
+use resources::WatchEvent;
+use errors::*;
+
+/// A blocking iterator over the events of a kubernetes watch
+///
+/// The iterator ends when the server closes the watch (e.g. once `timeoutSeconds` elapsed).
+pub struct WatchEvents<R> {
+    events: StreamDeserializer<'static, IoRead<reqwest::Response>, WatchEvent<R>>,
+}
+
+impl<R: DeserializeOwned> WatchEvents<R> {
+    pub(crate) fn new(response: reqwest::Response) -> WatchEvents<R> {
+        WatchEvents {
+            events: Deserializer::from_reader(response).into_iter(),
+        }
+    }
+}
+
+impl<R: DeserializeOwned> Iterator for WatchEvents<R> {
+    type Item = Result<WatchEvent<R>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.events.next()
+            .map(|event| event.chain_err(|| "Failed to decode watch event"))
+    }
+}
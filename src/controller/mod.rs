@@ -0,0 +1,308 @@
+//! A reconcile loop for writing operators on top of informers
+//!
+//! A [`Controller`](struct.Controller.html) watches a primary kind and the kinds it owns,
+//! queues the keys of primary resources that need attention in a
+//! [`WorkQueue`](struct.WorkQueue.html), and calls a user supplied `reconcile` function
+//! for each of them from a pool of worker threads.
+
+mod work_queue;
+
+pub use self::work_queue::*;
+
+use cache::*;
+use errors::*;
+use resources::*;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Identifies a primary resource queued for reconciliation
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjectRef {
+    pub namespace: Option<String>,
+    pub name: String,
+}
+
+impl ObjectRef {
+    pub fn new(namespace: Option<&str>, name: &str) -> ObjectRef {
+        ObjectRef { namespace: namespace.map(str::to_owned), name: name.to_owned() }
+    }
+
+    /// Builds the reference to a resource from its metadata
+    pub fn from_resource<R: Resource>(resource: &R) -> ObjectRef {
+        let metadata = resource.metadata();
+        ObjectRef {
+            namespace: metadata.namespace.clone(),
+            name: metadata.name.clone().unwrap_or_default(),
+        }
+    }
+
+    /// The key of the referenced resource in a `Store`
+    pub fn key(&self) -> String {
+        key(self.namespace.as_deref(), &self.name)
+    }
+}
+
+/// What the controller should do after a successful reconciliation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Action {
+    requeue_after: Option<Duration>,
+}
+
+impl Action {
+    /// Reconcile the resource again after `duration`, even if nothing changed
+    pub fn requeue(duration: Duration) -> Action {
+        Action { requeue_after: Some(duration) }
+    }
+
+    /// Only reconcile the resource again once it (or one of its owned resources) changes
+    pub fn await_change() -> Action {
+        Action { requeue_after: None }
+    }
+
+    pub fn requeue_after(&self) -> Option<Duration> {
+        self.requeue_after
+    }
+}
+
+/// Runs a reconcile function for every change to a primary kind or the kinds it owns
+///
+/// Failed reconciliations are handed to the `on_error` callback and retried with an
+/// exponential backoff, and a resource is never reconciled by two workers at the same time.
+///
+/// ## Examples
+///
+/// ```no_run
+/// # use qube::prelude::*;
+/// # use qube::cache::InformerFactory;
+/// # use qube::controller::{Action, Controller};
+/// # use qube::resources::{Deployment, ListQuery, Pod};
+/// # use std::time::Duration;
+/// let kube = Kubernetes::load_conf("admin.conf")?;
+/// let factory = InformerFactory::new(&kube, None);
+///
+/// Controller::<Deployment>::new(&factory, Some("default"), &ListQuery::default())
+///     .owns::<Pod>(Some("default"), &ListQuery::default())
+///     .workers(4)
+///     .on_error(|deployment, err| eprintln!("Failed to reconcile {:?}: {}", deployment.metadata.name, err))
+///     .run(|deployment| {
+///         println!("Reconciling {:?}", deployment.metadata.name);
+///         Ok(Action::requeue(Duration::from_secs(300)))
+///     });
+/// # Ok::<(), qube::Error>(())
+/// ```
+pub struct Controller<R> {
+    factory: InformerFactory,
+    primary: Informer<R>,
+    queue: WorkQueue<ObjectRef>,
+    workers: usize,
+    on_error: Option<Arc<ErrorFn<R>>>,
+}
+
+type ErrorFn<R> = dyn Fn(&R, &Error) + Send + Sync;
+
+impl<R> Controller<R>
+where R: ListableResource + Send + Sync + 'static
+{
+    /// Creates a controller for the primary resources selected by `query` in `namespace`
+    ///
+    /// Informers are taken from `factory`, so they are shared with its other consumers.
+    pub fn new(factory: &InformerFactory, namespace: Option<&str>, query: &ListQuery) -> Controller<R> {
+        let queue = WorkQueue::new();
+        let primary = factory.informer::<R>(namespace, query);
+
+        let (on_add, on_update, on_delete) = (queue.clone(), queue.clone(), queue.clone());
+        primary.add_event_handler(HandlerFuncs::new()
+            .on_add(move |r: &R| on_add.add(ObjectRef::from_resource(r)))
+            .on_update(move |_, r: &R| on_update.add(ObjectRef::from_resource(r)))
+            .on_delete(move |r: &R| on_delete.add(ObjectRef::from_resource(r))));
+
+        Controller { factory: factory.clone(), primary, queue, workers: 1, on_error: None }
+    }
+
+    /// Also reconciles a primary resource whenever a resource of kind `C` it owns changes
+    ///
+    /// Owned resources are mapped to their owner through `metadata.ownerReferences`.
+    pub fn owns<C>(self, namespace: Option<&str>, query: &ListQuery) -> Self
+    where C: ListableResource + Send + Sync + 'static
+    {
+        let owned = self.factory.informer::<C>(namespace, query);
+        let (on_add, on_update, on_delete) = (self.queue.clone(), self.queue.clone(), self.queue.clone());
+        owned.add_event_handler(HandlerFuncs::new()
+            .on_add(move |c: &C| enqueue_owners::<R, C>(&on_add, c))
+            .on_update(move |_, c: &C| enqueue_owners::<R, C>(&on_update, c))
+            .on_delete(move |c: &C| enqueue_owners::<R, C>(&on_delete, c)));
+        self
+    }
+
+    /// Sets the number of resources reconciled concurrently (defaults to 1)
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// Calls `on_error` with every failed reconciliation, before the resource is requeued
+    pub fn on_error<F>(mut self, on_error: F) -> Self
+    where F: Fn(&R, &Error) + Send + Sync + 'static
+    {
+        self.on_error = Some(Arc::new(on_error));
+        self
+    }
+
+    /// The queue of resources waiting to be reconciled
+    ///
+    /// Useful to trigger reconciliations from other sources, e.g. timers or webhooks.
+    pub fn queue(&self) -> WorkQueue<ObjectRef> {
+        self.queue.clone()
+    }
+
+    /// A handle for stopping the controller from another thread
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle { queue: self.queue.clone() }
+    }
+
+    /// Starts the informers and reconciles until the controller is shut down
+    ///
+    /// Blocks the current thread. Reconciliations start once every informer of the
+    /// factory synced, so that owned resources are cached too. On shutdown, in-flight reconciliations are allowed
+    /// to finish before `run` returns. The informers are left running, as they may be
+    /// shared; use `InformerFactory::shutdown` to stop them.
+    pub fn run<F>(self, reconcile: F)
+    where F: Fn(Arc<R>) -> Result<Action> + Send + Sync + 'static
+    {
+        self.factory.start();
+        while !self.factory.wait_for_cache_sync(Duration::from_secs(1)) {
+            if self.queue.is_shutting_down() {
+                return;
+            }
+        }
+
+        let reconcile = Arc::new(reconcile);
+        let workers: Vec<_> = (0..self.workers.max(1)).map(|_| {
+            let queue = self.queue.clone();
+            let store = self.primary.store();
+            let reconcile = reconcile.clone();
+            let on_error = self.on_error.clone();
+            thread::spawn(move || process(&queue, &store, &*reconcile, on_error.as_deref()))
+        }).collect();
+
+        for worker in workers {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Stops a running `Controller`
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    queue: WorkQueue<ObjectRef>,
+}
+
+impl ShutdownHandle {
+    pub fn shutdown(&self) {
+        self.queue.shut_down();
+    }
+}
+
+fn process<R, F>(queue: &WorkQueue<ObjectRef>, store: &Store<R>, reconcile: &F, on_error: Option<&ErrorFn<R>>)
+where R: Resource,
+      F: Fn(Arc<R>) -> Result<Action>,
+{
+    while let Some(obj_ref) = queue.get() {
+        match store.get(&obj_ref.key()) {
+            // The resource is gone, there is nothing left to reconcile
+            None => queue.forget(&obj_ref),
+            Some(resource) => match reconcile(resource.clone()) {
+                Ok(action) => {
+                    queue.forget(&obj_ref);
+                    if let Some(after) = action.requeue_after() {
+                        queue.add_after(obj_ref.clone(), after);
+                    }
+                }
+                Err(err) => {
+                    if let Some(on_error) = on_error {
+                        on_error(&resource, &err);
+                    }
+                    queue.add_rate_limited(obj_ref.clone());
+                }
+            },
+        }
+        queue.done(&obj_ref);
+    }
+}
+
+fn enqueue_owners<R: Resource, C: Resource>(queue: &WorkQueue<ObjectRef>, owned: &C) {
+    let metadata = owned.metadata();
+    // Kinds are only unique within a group, e.g. `Deployment` of `apps` and `extensions`,
+    // while any version of the group may have written the reference
+    let owners = metadata.owner_references.iter()
+        .flat_map(|refs| refs.iter())
+        .filter(|owner| owner.kind == R::KIND && api_group(&owner.api_version) == R::GROUP);
+    for owner in owners {
+        // Owners are always in the namespace of what they own (or cluster-scoped)
+        let namespace = if R::NAMESPACED { metadata.namespace.as_deref() } else { None };
+        queue.add(ObjectRef::new(namespace, &owner.name));
+    }
+}
+
+// The group of an `apiVersion`, which is empty for the core group (`v1`)
+fn api_group(api_version: &str) -> &str {
+    match api_version.rfind('/') {
+        Some(slash) => &api_version[..slash],
+        None => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_api::apimachinery::pkg::apis::meta::v1::OwnerReference;
+
+    fn owner(api_version: &str, kind: &str, name: &str) -> OwnerReference {
+        OwnerReference {
+            api_version: api_version.to_owned(),
+            kind: kind.to_owned(),
+            name: name.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    fn owned_pod(owners: Vec<OwnerReference>) -> Pod {
+        let mut pod = Pod::default();
+        pod.metadata.name = Some("web-1".to_owned());
+        pod.metadata.namespace = Some("prod".to_owned());
+        pod.metadata.owner_references = Some(owners);
+        pod
+    }
+
+    #[test]
+    fn owners_are_matched_on_kind_and_group() {
+        let queue = WorkQueue::new();
+        let pod = owned_pod(vec![
+            owner("apps/v1", "Deployment", "web"),
+            owner("apps/v1beta2", "Deployment", "api"),
+            owner("example.com/v1", "Deployment", "other"),
+            owner("apps/v1", "ReplicaSet", "web-5d4f"),
+        ]);
+        enqueue_owners::<Deployment, Pod>(&queue, &pod);
+
+        let mut queued = vec![queue.get().unwrap(), queue.get().unwrap()];
+        queued.sort();
+        assert_eq!(queued, vec![ObjectRef::new(Some("prod"), "api"), ObjectRef::new(Some("prod"), "web")]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn cluster_scoped_owners_have_no_namespace() {
+        let queue = WorkQueue::new();
+        enqueue_owners::<Node, Pod>(&queue, &owned_pod(vec![owner("v1", "Node", "worker-1")]));
+        assert_eq!(queue.get(), Some(ObjectRef::new(None, "worker-1")));
+    }
+
+    #[test]
+    fn api_groups() {
+        assert_eq!(api_group("v1"), "");
+        assert_eq!(api_group("apps/v1"), "apps");
+        assert_eq!(api_group("networking.k8s.io/v1"), "networking.k8s.io");
+    }
+}
@@ -0,0 +1,291 @@
+use std::cmp;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+const BASE_DELAY_MILLIS: u64 = 5;
+const MAX_DELAY_SECS: u64 = 1000;
+
+/// A deduplicating, rate-limited work queue shared between controller workers
+///
+/// An item is never queued twice, and an item is never handed to two workers at once:
+/// items added while being processed are queued again once the worker calls `done`.
+/// Queues are cheap to clone; all clones share the same items.
+pub struct WorkQueue<T> {
+    inner: Arc<(Mutex<QueueState<T>>, Condvar)>,
+    base_delay: Duration,
+    max_delay: Duration,
+}
+
+struct QueueState<T> {
+    queue: VecDeque<T>,
+    dirty: HashSet<T>,
+    processing: HashSet<T>,
+    // At most one deadline per item, the earliest requested
+    waiting: HashMap<T, Instant>,
+    failures: HashMap<T, u32>,
+    shutting_down: bool,
+}
+
+impl<T> Clone for WorkQueue<T> {
+    fn clone(&self) -> Self {
+        WorkQueue { inner: self.inner.clone(), base_delay: self.base_delay, max_delay: self.max_delay }
+    }
+}
+
+impl<T: Hash + Eq + Clone> WorkQueue<T> {
+    /// Creates a queue whose rate limiter backs off from 5ms up to 1000s
+    pub fn new() -> WorkQueue<T> {
+        WorkQueue::with_backoff(Duration::from_millis(BASE_DELAY_MILLIS), Duration::from_secs(MAX_DELAY_SECS))
+    }
+
+    /// Creates a queue whose rate limiter doubles `base_delay` on every failure, up to `max_delay`
+    pub fn with_backoff(base_delay: Duration, max_delay: Duration) -> WorkQueue<T> {
+        let state = QueueState {
+            queue: VecDeque::new(),
+            dirty: HashSet::new(),
+            processing: HashSet::new(),
+            waiting: HashMap::new(),
+            failures: HashMap::new(),
+            shutting_down: false,
+        };
+        WorkQueue { inner: Arc::new((Mutex::new(state), Condvar::new())), base_delay, max_delay }
+    }
+
+    /// Queues an item, unless it is already queued
+    pub fn add(&self, item: T) {
+        let (ref lock, ref cvar) = *self.inner;
+        let mut state = lock.lock().unwrap();
+        state.add(item);
+        cvar.notify_one();
+    }
+
+    /// Queues an item once `delay` elapsed
+    ///
+    /// An item waits at most once: adding it again keeps the earlier deadline.
+    pub fn add_after(&self, item: T, delay: Duration) {
+        let (ref lock, ref cvar) = *self.inner;
+        let mut state = lock.lock().unwrap();
+        if state.shutting_down {
+            return;
+        }
+        let deadline = Instant::now() + delay;
+        let waiting = state.waiting.entry(item).or_insert(deadline);
+        *waiting = cmp::min(*waiting, deadline);
+        // Wake a worker so that it waits for the new deadline
+        cvar.notify_one();
+    }
+
+    /// Queues an item after an exponential backoff based on how often it was requeued
+    pub fn add_rate_limited(&self, item: T) {
+        let delay = {
+            let (ref lock, _) = *self.inner;
+            let mut state = lock.lock().unwrap();
+            let failures = state.failures.entry(item.clone()).or_insert(0);
+            let exp = cmp::min(*failures, 31);
+            *failures += 1;
+            cmp::min(self.base_delay * 2u32.pow(exp), self.max_delay)
+        };
+        self.add_after(item, delay);
+    }
+
+    /// Resets the backoff of an item, e.g. after it was processed successfully
+    pub fn forget(&self, item: &T) {
+        let (ref lock, _) = *self.inner;
+        lock.lock().unwrap().failures.remove(item);
+    }
+
+    /// The number of times an item was requeued through `add_rate_limited`
+    pub fn num_requeues(&self, item: &T) -> u32 {
+        let (ref lock, _) = *self.inner;
+        lock.lock().unwrap().failures.get(item).cloned().unwrap_or(0)
+    }
+
+    /// Blocks until an item is available
+    ///
+    /// Returns `None` once the queue is shut down. Every item returned must be
+    /// handed back through `done` when processing finished.
+    pub fn get(&self) -> Option<T> {
+        let (ref lock, ref cvar) = *self.inner;
+        let mut state = lock.lock().unwrap();
+        loop {
+            if state.shutting_down {
+                return None;
+            }
+            let next_deadline = state.promote_waiting();
+            if let Some(item) = state.queue.pop_front() {
+                state.dirty.remove(&item);
+                state.processing.insert(item.clone());
+                return Some(item);
+            }
+            state = match next_deadline {
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    cvar.wait_timeout(state, timeout).unwrap().0
+                }
+                None => cvar.wait(state).unwrap(),
+            };
+        }
+    }
+
+    /// Marks an item as processed, queueing it again if it was added in the meantime
+    pub fn done(&self, item: &T) {
+        let (ref lock, ref cvar) = *self.inner;
+        let mut state = lock.lock().unwrap();
+        state.processing.remove(item);
+        if state.dirty.contains(item) {
+            state.queue.push_back(item.clone());
+            cvar.notify_one();
+        }
+    }
+
+    /// The number of items ready to be processed
+    pub fn len(&self) -> usize {
+        let (ref lock, _) = *self.inner;
+        lock.lock().unwrap().queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Stops handing out items; workers blocked in `get` return `None`
+    pub fn shut_down(&self) {
+        let (ref lock, ref cvar) = *self.inner;
+        lock.lock().unwrap().shutting_down = true;
+        cvar.notify_all();
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        let (ref lock, _) = *self.inner;
+        lock.lock().unwrap().shutting_down
+    }
+}
+
+impl<T: Hash + Eq + Clone> Default for WorkQueue<T> {
+    fn default() -> Self {
+        WorkQueue::new()
+    }
+}
+
+impl<T: Hash + Eq + Clone> QueueState<T> {
+    fn add(&mut self, item: T) {
+        if self.shutting_down || self.dirty.contains(&item) {
+            return;
+        }
+        self.dirty.insert(item.clone());
+        if !self.processing.contains(&item) {
+            self.queue.push_back(item);
+        }
+    }
+
+    // Moves delayed items whose deadline passed into the queue,
+    // returning the deadline of the next delayed item
+    fn promote_waiting(&mut self) -> Option<Instant> {
+        let now = Instant::now();
+        let (ready, waiting): (HashMap<_, _>, HashMap<_, _>) = self.waiting.drain()
+            .partition(|&(_, deadline)| deadline <= now);
+        self.waiting = waiting;
+        let mut ready: Vec<_> = ready.into_iter().collect();
+        ready.sort_by_key(|&(_, deadline)| deadline);
+        for (item, _) in ready {
+            self.add(item);
+        }
+        self.waiting.values().min().cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn queued_items_are_deduplicated() {
+        let queue = WorkQueue::new();
+        queue.add("a");
+        queue.add("b");
+        queue.add("a");
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.get(), Some("a"));
+        assert_eq!(queue.get(), Some("b"));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn items_added_while_processing_wait_for_done() {
+        let queue = WorkQueue::new();
+        queue.add("a");
+        assert_eq!(queue.get(), Some("a"));
+        queue.add("a");
+        queue.add("a");
+        assert!(queue.is_empty());
+
+        queue.done(&"a");
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.get(), Some("a"));
+        queue.done(&"a");
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_max_delay() {
+        let queue = WorkQueue::with_backoff(Duration::from_millis(10), Duration::from_millis(40));
+        for expected in &[10, 20, 40, 40] {
+            let start = Instant::now();
+            queue.add_rate_limited("a");
+            assert_eq!(queue.get(), Some("a"));
+            let elapsed = start.elapsed();
+            assert!(elapsed >= Duration::from_millis(*expected), "{:?} < {}ms", elapsed, expected);
+            queue.done(&"a");
+        }
+        assert_eq!(queue.num_requeues(&"a"), 4);
+
+        queue.forget(&"a");
+        assert_eq!(queue.num_requeues(&"a"), 0);
+    }
+
+    #[test]
+    fn delayed_items_are_not_handed_out_early() {
+        let queue = WorkQueue::new();
+        queue.add_after("late", Duration::from_millis(50));
+        queue.add("now");
+        let start = Instant::now();
+        assert_eq!(queue.get(), Some("now"));
+        assert_eq!(queue.get(), Some("late"));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn delayed_items_wait_once_until_the_earliest_deadline() {
+        let queue = WorkQueue::new();
+        queue.add_after("a", Duration::from_millis(200));
+        queue.add_after("a", Duration::from_millis(20));
+        queue.add_after("a", Duration::from_millis(100));
+        assert_eq!(queue.inner.0.lock().unwrap().waiting.len(), 1);
+
+        let start = Instant::now();
+        assert_eq!(queue.get(), Some("a"));
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(20) && elapsed < Duration::from_millis(100), "{:?}", elapsed);
+        queue.done(&"a");
+        assert!(queue.inner.0.lock().unwrap().waiting.is_empty());
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn shut_down_releases_blocked_workers() {
+        let queue = WorkQueue::<&str>::new();
+        let worker = {
+            let queue = queue.clone();
+            thread::spawn(move || queue.get())
+        };
+        thread::sleep(Duration::from_millis(20));
+        queue.shut_down();
+        assert_eq!(worker.join().unwrap(), None);
+
+        queue.add("a");
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,239 @@
+use super::Discovery;
+use errors::*;
+use k8s_api::apimachinery::pkg::apis::meta::v1::{APIGroup, APIGroupList, APIResourceList};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use url::Url;
+
+/// How long `kubectl` considers its discovery cache fresh
+pub const DEFAULT_DISCOVERY_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+const SERVER_GROUPS_FILE: &str = "servergroups.json";
+const SERVER_RESOURCES_FILE: &str = "serverresources.json";
+
+/// Keeps discovery results on disk, in the same layout as `kubectl`:
+/// `servergroups.json` for the groups, and `<group>/<version>/serverresources.json`
+/// for the resources of each group version
+///
+/// Only these files are ever read, written or removed, so other files in the directory are left alone.
+///
+/// ## Examples
+///
+/// ```no_run
+/// # use qube::prelude::*;
+/// # use qube::discovery::DiscoveryCache;
+/// # use std::time::Duration;
+/// let kube = Kubernetes::load_conf("admin.conf")?;
+/// let cache = DiscoveryCache::new("/tmp/discovery", Duration::from_secs(600));
+/// let kube = kube.discovery_cache(Some(cache));
+/// let mapper = kube.rest_mapper()?;
+/// # Ok::<(), qube::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct DiscoveryCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl DiscoveryCache {
+    pub fn new<P: AsRef<Path>>(dir: P, ttl: Duration) -> DiscoveryCache {
+        DiscoveryCache { dir: dir.as_ref().to_owned(), ttl }
+    }
+
+    /// The cache `kubectl` uses for the API server at `server`,
+    /// i.e. `~/.kube/cache/discovery/<host>_<port>/`
+    ///
+    /// Returns `None` when the home directory is unknown.
+    pub fn for_server(server: &Url) -> Option<DiscoveryCache> {
+        let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?;
+        let host = match (server.host_str(), server.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_owned(),
+            (None, _) => return None,
+        };
+        // Same as kubectl, which replaces any character that might be illegal in a path
+        let host: String = host.chars()
+            .map(|c| if c.is_alphanumeric() || c == '.' || c == '_' { c } else { '_' })
+            .collect();
+        let dir = Path::new(&home).join(".kube").join("cache").join("discovery").join(host);
+        Some(DiscoveryCache::new(dir, DEFAULT_DISCOVERY_TTL))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Reads the cached discovery, unless any of its files is missing or older than the TTL
+    pub fn load(&self) -> Option<Discovery> {
+        let groups: APIGroupList = self.read_fresh(&self.dir.join(SERVER_GROUPS_FILE))?;
+        let mut resources = BTreeMap::new();
+        for version in groups.groups.iter().flat_map(|group| group.versions.iter()) {
+            let list: APIResourceList = self.read_fresh(&self.resources_file(&version.group_version))?;
+            resources.insert(list.group_version, list.resources);
+        }
+        Some(Discovery { groups: groups.groups, resources, unavailable: Vec::new() })
+    }
+
+    /// Writes a discovery to the cache
+    ///
+    /// Unavailable group versions are left out, so that the rest of the discovery can be
+    /// loaded back. They are discovered again when a lookup misses (see `Kubernetes::check_served`).
+    pub fn store(&self, discovery: &Discovery) -> Result<()> {
+        let groups = APIGroupList {
+            groups: discovery.groups.iter().filter_map(|group| available_versions(group, discovery)).collect(),
+        };
+        write_json(&self.dir.join(SERVER_GROUPS_FILE), &groups)?;
+        for (group_version, resources) in &discovery.resources {
+            let list = APIResourceList {
+                group_version: group_version.clone(),
+                resources: resources.clone(),
+            };
+            write_json(&self.resources_file(group_version), &list)?;
+        }
+        Ok(())
+    }
+
+    /// Removes the cached discovery, so that the next one hits the server
+    pub fn invalidate(&self) -> Result<()> {
+        let groups_file = self.dir.join(SERVER_GROUPS_FILE);
+        let groups: Option<APIGroupList> = File::open(&groups_file).ok()
+            .and_then(|file| serde_json::from_reader(file).ok());
+        let versions = groups.iter().flat_map(|groups| groups.groups.iter()).flat_map(|group| group.versions.iter());
+        for version in versions {
+            remove_file(&self.resources_file(&version.group_version))?;
+        }
+        remove_file(&groups_file)
+    }
+
+    fn resources_file(&self, group_version: &str) -> PathBuf {
+        self.dir.join(group_version).join(SERVER_RESOURCES_FILE)
+    }
+
+    fn read_fresh<D: DeserializeOwned>(&self, path: &Path) -> Option<D> {
+        let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok()?;
+        let age = SystemTime::now().duration_since(modified).unwrap_or_default();
+        if age > self.ttl {
+            return None;
+        }
+        serde_json::from_reader(File::open(path).ok()?).ok()
+    }
+}
+
+// The group without its unavailable versions, or `None` if none is available
+fn available_versions(group: &APIGroup, discovery: &Discovery) -> Option<APIGroup> {
+    let mut group = group.clone();
+    group.versions.retain(|version| discovery.resources.contains_key(&version.group_version));
+    if group.versions.is_empty() {
+        return None;
+    }
+    if !group.preferred_version.as_ref().is_some_and(|preferred| group.versions.contains(preferred)) {
+        group.preferred_version = group.versions.first().cloned();
+    }
+    Some(group)
+}
+
+fn remove_file(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(ref e) if e.kind() != io::ErrorKind::NotFound => {
+            bail!("Failed to remove discovery cache {}: {}", path.display(), e)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn write_json<S: Serialize>(path: &Path, value: &S) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let file = File::create(path)?;
+    serde_json::to_writer(file, value)
+        .chain_err(|| format!("Failed to write discovery cache {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn discovery() -> Discovery {
+        let groups = serde_json::from_value(json!([
+            { "name": "", "versions": [{ "groupVersion": "v1", "version": "v1" }] },
+            { "name": "apps", "versions": [{ "groupVersion": "apps/v1", "version": "v1" }] }
+        ])).unwrap();
+        let pods = json!([{ "name": "pods", "singularName": "pod", "namespaced": true, "kind": "Pod", "verbs": ["get"] }]);
+        let deployments = json!([{ "name": "deployments", "singularName": "deployment", "namespaced": true, "kind": "Deployment", "verbs": ["get"] }]);
+        let mut resources = BTreeMap::new();
+        resources.insert("v1".to_owned(), serde_json::from_value(pods).unwrap());
+        resources.insert("apps/v1".to_owned(), serde_json::from_value(deployments).unwrap());
+        Discovery { groups, resources, unavailable: Vec::new() }
+    }
+
+    fn cache(name: &str) -> DiscoveryCache {
+        let dir = env::temp_dir().join(format!("qube-discovery-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        DiscoveryCache::new(dir, DEFAULT_DISCOVERY_TTL)
+    }
+
+    #[test]
+    fn stored_discovery_is_loaded_back() {
+        let cache = cache("round-trip");
+        cache.store(&discovery()).unwrap();
+        let loaded = cache.load().unwrap();
+        assert_eq!(loaded.preferred_version("apps"), Some("v1"));
+        assert!(loaded.is_served("v1", "pods"));
+        assert!(loaded.is_served("apps/v1", "deployments"));
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+
+    #[test]
+    fn partial_caches_are_not_loaded() {
+        let cache = cache("partial");
+        cache.store(&discovery()).unwrap();
+        fs::remove_file(cache.dir().join("apps/v1").join(SERVER_RESOURCES_FILE)).unwrap();
+        assert!(cache.load().is_none());
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+
+    #[test]
+    fn unavailable_group_versions_are_left_out() {
+        let cache = cache("unavailable");
+        let mut discovery = discovery();
+        discovery.groups.push(serde_json::from_value(json!({
+            "name": "metrics.k8s.io",
+            "versions": [{ "groupVersion": "metrics.k8s.io/v1beta1", "version": "v1beta1" }],
+            "preferredVersion": { "groupVersion": "metrics.k8s.io/v1beta1", "version": "v1beta1" }
+        })).unwrap());
+        discovery.groups[1].versions.insert(0, serde_json::from_value(json!({ "groupVersion": "apps/v2", "version": "v2" })).unwrap());
+        discovery.groups[1].preferred_version = discovery.groups[1].versions.first().cloned();
+        discovery.unavailable = vec!["apps/v2".to_owned(), "metrics.k8s.io/v1beta1".to_owned()];
+
+        cache.store(&discovery).unwrap();
+        let loaded = cache.load().unwrap();
+        assert_eq!(loaded.preferred_version("apps"), Some("v1"));
+        assert_eq!(loaded.preferred_version("metrics.k8s.io"), None);
+        assert!(loaded.is_served("apps/v1", "deployments"));
+        assert_eq!(loaded.groups().len(), 2);
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+
+    #[test]
+    fn invalidating_leaves_other_files_alone() {
+        let cache = cache("invalidate");
+        cache.store(&discovery()).unwrap();
+        let other = cache.dir().join("openapi.json");
+        fs::write(&other, "{}").unwrap();
+
+        cache.invalidate().unwrap();
+        assert!(cache.load().is_none());
+        assert!(!cache.dir().join(SERVER_GROUPS_FILE).exists());
+        assert!(other.exists());
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+//! Discovery of the kinds a cluster serves
+//!
+//! [`Discovery`](struct.Discovery.html) reads the API groups, versions and resources
+//! from `/api` and `/apis`, and a [`RestMapper`](struct.RestMapper.html) built from it maps
+//! any `apiVersion` and `kind` to the URLs of its resources. Discovery results are cached
+//! on disk (see [`DiscoveryCache`](struct.DiscoveryCache.html)).
+//!
+//! ## Examples
+//!
+//! ```no_run
+//! # use qube::prelude::*;
+//! let kube = Kubernetes::load_conf("admin.conf")?;
+//! let mapper = kube.rest_mapper()?;
+//! let certificates = mapper.mapping("cert-manager.io/v1", "Certificate")
+//!     .expect("cert-manager is not installed");
+//! let certificates = kube.dynamic(certificates.clone()).list(None)?;
+//! # Ok::<(), qube::Error>(())
+//! ```
+
+mod cache;
+mod rest_mapper;
+mod version;
+
+pub use self::cache::*;
+pub use self::rest_mapper::*;
+pub use self::version::*;
+
+use clients::Kubernetes;
+use errors::*;
+use k8s_api::apimachinery::pkg::apis::meta::v1::{APIGroup, APIGroupList, APIResource, APIResourceList, APIVersions, GroupVersionForDiscovery};
+use std::collections::BTreeMap;
+
+/// The API groups, versions and resources served by a cluster
+#[derive(Clone, Debug)]
+pub struct Discovery {
+    groups: Vec<APIGroup>,
+    resources: BTreeMap<String, Vec<APIResource>>,
+    unavailable: Vec<String>,
+}
+
+impl Discovery {
+    /// Discovers everything the cluster serves
+    ///
+    /// This sends a request per group version. Group versions that fail to answer
+    /// (e.g. an aggregated API whose backend is down) are skipped, see `unavailable`.
+    pub fn run(kube: &Kubernetes) -> Result<Discovery> {
+        let low_level = &kube.low_level;
+        let core: APIVersions = low_level.http_get_json(low_level.base_url.join("/api")?)
+            .chain_err(|| "Failed to discover the core API versions")?;
+        let apis: APIGroupList = low_level.http_get_json(low_level.base_url.join("/apis")?)
+            .chain_err(|| "Failed to discover the API groups")?;
+
+        // The core group is served apart, under `/api`, and has no name
+        let core_versions: Vec<GroupVersionForDiscovery> = core.versions.iter()
+            .map(|version| GroupVersionForDiscovery { group_version: version.clone(), version: version.clone() })
+            .collect();
+        let core = APIGroup {
+            name: String::new(),
+            preferred_version: core_versions.first().cloned(),
+            server_address_by_client_cidrs: None,
+            versions: core_versions,
+        };
+        let groups: Vec<APIGroup> = Some(core).into_iter().chain(apis.groups).collect();
+
+        let mut resources = BTreeMap::new();
+        let mut unavailable = Vec::new();
+        for version in groups.iter().flat_map(|group| group.versions.iter()) {
+            let path = if version.group_version.contains('/') {
+                format!("/apis/{}", version.group_version)
+            } else {
+                format!("/api/{}", version.group_version)
+            };
+            match low_level.http_get_json::<APIResourceList>(low_level.base_url.join(&path)?) {
+                Ok(list) => {
+                    resources.insert(list.group_version, list.resources);
+                }
+                Err(_) => unavailable.push(version.group_version.clone()),
+            }
+        }
+
+        Ok(Discovery { groups, resources, unavailable })
+    }
+
+    /// The API groups, starting with the core group (whose name is empty)
+    pub fn groups(&self) -> &[APIGroup] {
+        &self.groups
+    }
+
+    /// The version of `group` that clients should use, e.g. `v1`
+    pub fn preferred_version(&self, group: &str) -> Option<&str> {
+        self.groups.iter()
+            .find(|g| g.name == group)
+            .and_then(|g| g.preferred_version.as_ref().or_else(|| g.versions.first()))
+            .map(|version| version.version.as_str())
+    }
+
+    /// The resources served in a group version, e.g. `apps/v1`, including subresources
+    /// (whose names contain a slash, e.g. `deployments/scale`)
+    pub fn resources(&self, group_version: &str) -> &[APIResource] {
+        self.resources.get(group_version).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Whether a group version, e.g. `apps/v1`, serves the resources named `plural`
+    pub fn is_served(&self, group_version: &str, plural: &str) -> bool {
+        self.resources(group_version).iter().any(|resource| resource.name == plural)
+    }
+
+    /// The group versions that could not be discovered
+    pub fn unavailable(&self) -> &[String] {
+        &self.unavailable
+    }
+
+    /// Builds a `RestMapper` out of the discovered resources
+    pub fn rest_mapper(&self) -> RestMapper {
+        RestMapper::new(self)
+    }
+}
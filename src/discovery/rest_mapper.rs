@@ -0,0 +1,121 @@
+use super::Discovery;
+use resources::ApiResource;
+use std::collections::HashMap;
+
+/// Maps the `apiVersion` and `kind` of resources to where the cluster serves them
+///
+/// Built from a [`Discovery`](struct.Discovery.html).
+#[derive(Clone, Debug, Default)]
+pub struct RestMapper {
+    // By `apiVersion` and `kind`
+    mappings: HashMap<(String, String), ApiResource>,
+    // By group and `kind`, in the preferred version of the group
+    preferred: HashMap<(String, String), ApiResource>,
+    // By plural, singular, short name and lowercase kind, in preferred versions
+    names: HashMap<String, ApiResource>,
+}
+
+impl RestMapper {
+    pub fn new(discovery: &Discovery) -> RestMapper {
+        let mut mapper = RestMapper::default();
+        for group in discovery.groups() {
+            let preferred = discovery.preferred_version(&group.name);
+            for version in &group.versions {
+                for resource in discovery.resources(&version.group_version) {
+                    // Subresources, e.g. `deployments/scale`, are not kinds of their own
+                    if resource.name.contains('/') {
+                        continue;
+                    }
+                    let api_resource = ApiResource::new(&group.name, &version.version, &resource.kind, &resource.name, resource.namespaced);
+                    mapper.mappings.entry((version.group_version.clone(), resource.kind.clone()))
+                        .or_insert_with(|| api_resource.clone());
+                    if preferred != Some(version.version.as_str()) {
+                        continue;
+                    }
+
+                    mapper.preferred.entry((group.name.clone(), resource.kind.clone()))
+                        .or_insert_with(|| api_resource.clone());
+                    let short_names = resource.short_names.iter().flat_map(|names| names.iter().cloned());
+                    let names = vec![resource.name.clone(), resource.singular_name.clone(), resource.kind.to_lowercase()];
+                    // Earlier groups win, so that e.g. `events` are the core ones
+                    for name in names.into_iter().chain(short_names).filter(|name| !name.is_empty()) {
+                        mapper.names.entry(name).or_insert_with(|| api_resource.clone());
+                    }
+                }
+            }
+        }
+        mapper
+    }
+
+    /// The kind with this `apiVersion` (e.g. `apps/v1`) and `kind` (e.g. `Deployment`)
+    pub fn mapping(&self, api_version: &str, kind: &str) -> Option<&ApiResource> {
+        self.mappings.get(&(api_version.to_owned(), kind.to_owned()))
+    }
+
+    /// The kind in the preferred version of `group` (empty for the core group)
+    pub fn preferred_mapping(&self, group: &str, kind: &str) -> Option<&ApiResource> {
+        self.preferred.get(&(group.to_owned(), kind.to_owned()))
+    }
+
+    /// The kind named like on the `kubectl` command line, e.g. `deployments`, `deployment` or `deploy`
+    pub fn resource_for(&self, name: &str) -> Option<&ApiResource> {
+        self.names.get(&name.to_lowercase())
+    }
+
+    /// All the known kinds, in every version
+    pub fn mappings(&self) -> impl Iterator<Item = &ApiResource> {
+        self.mappings.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{self, json};
+    use std::collections::BTreeMap;
+
+    fn discovery() -> Discovery {
+        let groups = serde_json::from_value(json!([
+            { "name": "", "versions": [{ "groupVersion": "v1", "version": "v1" }] },
+            {
+                "name": "apps",
+                "versions": [
+                    { "groupVersion": "apps/v1", "version": "v1" },
+                    { "groupVersion": "apps/v1beta2", "version": "v1beta2" }
+                ],
+                "preferredVersion": { "groupVersion": "apps/v1", "version": "v1" }
+            },
+            { "name": "events.k8s.io", "versions": [{ "groupVersion": "events.k8s.io/v1", "version": "v1" }] }
+        ])).unwrap();
+        let deployments = json!({
+            "name": "deployments", "singularName": "deployment", "namespaced": true,
+            "kind": "Deployment", "verbs": ["get", "list"], "shortNames": ["deploy"]
+        });
+        let scale = json!({ "name": "deployments/scale", "singularName": "", "namespaced": true, "kind": "Scale", "verbs": ["get"] });
+        let events = json!({ "name": "events", "singularName": "event", "namespaced": true, "kind": "Event", "verbs": ["get"] });
+        let mut resources = BTreeMap::new();
+        resources.insert("v1".to_owned(), serde_json::from_value(json!([events])).unwrap());
+        resources.insert("apps/v1".to_owned(), serde_json::from_value(json!([deployments, scale])).unwrap());
+        resources.insert("apps/v1beta2".to_owned(), serde_json::from_value(json!([deployments])).unwrap());
+        resources.insert("events.k8s.io/v1".to_owned(), serde_json::from_value(json!([events])).unwrap());
+        Discovery { groups, resources, unavailable: Vec::new() }
+    }
+
+    #[test]
+    fn kinds_are_mapped_in_every_version() {
+        let mapper = RestMapper::new(&discovery());
+        assert_eq!(mapper.mapping("apps/v1beta2", "Deployment").map(ApiResource::api_version), Some("apps/v1beta2".to_owned()));
+        assert_eq!(mapper.preferred_mapping("apps", "Deployment").map(ApiResource::api_version), Some("apps/v1".to_owned()));
+        assert!(mapper.mapping("apps/v1", "Scale").is_none());
+    }
+
+    #[test]
+    fn kinds_are_found_by_their_command_line_names() {
+        let mapper = RestMapper::new(&discovery());
+        for name in &["deployments", "deployment", "deploy", "Deployment"] {
+            assert_eq!(mapper.resource_for(name).map(ApiResource::api_version), Some("apps/v1".to_owned()));
+        }
+        assert_eq!(mapper.resource_for("events").map(ApiResource::api_version), Some("v1".to_owned()));
+        assert!(mapper.resource_for("scale").is_none());
+    }
+}
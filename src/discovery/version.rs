@@ -0,0 +1,84 @@
+/// The version of the API server, as reported by `/version`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerVersion {
+    /// e.g. `1`
+    pub major: String,
+
+    /// e.g. `27`, or `27+` on some managed clusters
+    pub minor: String,
+
+    /// e.g. `v1.27.3`
+    pub git_version: String,
+
+    #[serde(default)]
+    pub git_commit: String,
+
+    #[serde(default)]
+    pub build_date: String,
+
+    #[serde(default)]
+    pub go_version: String,
+
+    /// e.g. `linux/amd64`
+    pub platform: String,
+}
+
+impl ServerVersion {
+    /// The major version as a number
+    pub fn major_number(&self) -> Option<u32> {
+        leading_number(&self.major)
+    }
+
+    /// The minor version as a number, ignoring suffixes such as the `+` of `27+`
+    pub fn minor_number(&self) -> Option<u32> {
+        leading_number(&self.minor)
+    }
+
+    /// Whether the server is at least version `major.minor`
+    pub fn is_at_least(&self, major: u32, minor: u32) -> bool {
+        match (self.major_number(), self.minor_number()) {
+            (Some(server_major), Some(server_minor)) => (server_major, server_minor) >= (major, minor),
+            _ => false,
+        }
+    }
+}
+
+fn leading_number(version: &str) -> Option<u32> {
+    let digits: String = version.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn version(major: &str, minor: &str) -> ServerVersion {
+        let json = format!(r#"{{"major": "{}", "minor": "{}", "gitVersion": "v1.27.3", "platform": "linux/amd64"}}"#, major, minor);
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn suffixes_are_ignored() {
+        let version = version("1", "27+");
+        assert_eq!(version.major_number(), Some(1));
+        assert_eq!(version.minor_number(), Some(27));
+    }
+
+    #[test]
+    fn versions_are_compared_numerically() {
+        let version = version("1", "9");
+        assert!(version.is_at_least(1, 9));
+        assert!(version.is_at_least(1, 8));
+        assert!(!version.is_at_least(1, 10));
+        assert!(!version.is_at_least(2, 0));
+    }
+
+    #[test]
+    fn unparseable_versions_are_never_recent_enough() {
+        let version = version("", "");
+        assert_eq!(version.minor_number(), None);
+        assert!(!version.is_at_least(0, 0));
+    }
+}
@@ -1,4 +1,10 @@
+use k8s_api::apimachinery::pkg::apis::meta::v1::StatusDetails;
+use resources::Status;
+use serde_json;
+use std::fmt;
 
+// Bodies of non-`Status` error responses (e.g. HTML from a proxy) are cut to this length
+const MAX_BODY_MESSAGE_LEN: usize = 256;
 
 error_chain! {
     foreign_links {
@@ -8,4 +14,372 @@ error_chain! {
         Url(::url::ParseError);
         Http(::reqwest::Error);
     }
+
+    errors {
+        /// The Kubernetes API rejected a request
+        Api(err: Box<ApiError>) {
+            description("Kubernetes API error")
+            display("Kubernetes API error: {}", err)
+        }
+
+        /// The API server doesn't serve the group version of a typed resource,
+        /// e.g. a kind removed from the server, or whose CRD is not installed
+        NotServed(kind: String, api_version: String, preferred_version: Option<String>) {
+            description("kind not served by the Kubernetes API server")
+            display("{} {} is not served by the Kubernetes API server{}", api_version, kind,
+                preferred_version.as_ref().map(|v| format!(", which serves {}", v)).unwrap_or_default())
+        }
+
+        /// A server-side apply tried to change fields owned by other field managers
+        ApplyConflict(err: Box<ApiError>, conflicts: Vec<FieldManagerConflict>) {
+            description("server-side apply conflict")
+            display("Apply conflict: {}", err)
+        }
+    }
+}
+
+/// A failure reported by the Kubernetes API, decoded from its `Status` response
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApiError {
+    /// The HTTP status code of the response
+    pub code: u16,
+
+    /// A machine-readable description of the failure, e.g. `NotFound` or `Conflict`
+    ///
+    /// When the server didn't report one, it is derived from the HTTP status code.
+    pub reason: String,
+
+    /// A human-readable description of the failure
+    pub message: String,
+
+    /// Extended data about the failure, e.g. the fields that failed validation (`details.causes`)
+    pub details: Option<StatusDetails>,
+}
+
+/// A field that a server-side apply could not set, because another field manager owns it
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldManagerConflict {
+    /// The field manager owning the field, e.g. `kubectl`
+    pub manager: String,
+
+    /// The path of the field, e.g. `.spec.replicas`
+    pub field: String,
+
+    /// The message reported by the server, e.g. `conflict with "kubectl" using apps/v1`
+    pub message: String,
+}
+
+impl ApiError {
+    /// Decodes an error response, falling back to a generic error
+    /// when the body is not a `Status` (e.g. an HTML page from a proxy)
+    pub fn from_response_body(code: u16, body: &str) -> ApiError {
+        match serde_json::from_str::<Status>(body) {
+            Ok(mut status) if status.kind == "Status" => {
+                if status.code.unwrap_or_default() == 0 {
+                    status.code = Some(code);
+                }
+                ApiError::from(&status)
+            }
+            _ => {
+                let body = body.trim();
+                let message = if body.is_empty() {
+                    format!("the server responded with status {} and no body", code)
+                } else {
+                    let body: String = body.chars().take(MAX_BODY_MESSAGE_LEN).collect();
+                    format!("the server responded with status {} and an unexpected body: {}", code, body)
+                };
+                ApiError { code, reason: reason_for_code(code).to_owned(), message, details: None }
+            }
+        }
+    }
+
+    /// The conflicts with other field managers reported by a server-side apply
+    pub fn field_manager_conflicts(&self) -> Vec<FieldManagerConflict> {
+        let causes = self.details.as_ref().and_then(|d| d.causes.as_ref());
+        causes.into_iter()
+            .flat_map(|c| c.iter())
+            .filter(|cause| cause.reason.as_deref() == Some("FieldManagerConflict"))
+            .map(|cause| {
+                let message = cause.message.clone().unwrap_or_default();
+                // The manager is quoted in the message: `conflict with "kubectl" using apps/v1`
+                let manager = message.split('"').nth(1).unwrap_or_default().to_owned();
+                FieldManagerConflict { manager, field: cause.field.clone().unwrap_or_default(), message }
+            })
+            .collect()
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.reason == "NotFound"
+    }
+
+    pub fn is_already_exists(&self) -> bool {
+        self.reason == "AlreadyExists"
+    }
+
+    pub fn is_conflict(&self) -> bool {
+        self.reason == "Conflict"
+    }
+
+    pub fn is_invalid(&self) -> bool {
+        self.reason == "Invalid"
+    }
+
+    pub fn is_forbidden(&self) -> bool {
+        self.reason == "Forbidden"
+    }
+
+    pub fn is_unauthorized(&self) -> bool {
+        self.reason == "Unauthorized"
+    }
+
+    /// Indicates that a `resourceVersion` (e.g. of a watch) is too old
+    pub fn is_gone(&self) -> bool {
+        self.reason == "Expired" || self.reason == "Gone"
+    }
+
+    pub fn is_too_many_requests(&self) -> bool {
+        self.reason == "TooManyRequests"
+    }
+
+    pub fn is_server_timeout(&self) -> bool {
+        self.reason == "ServerTimeout" || self.reason == "Timeout"
+    }
+}
+
+impl From<ApiError> for ErrorKind {
+    fn from(err: ApiError) -> ErrorKind {
+        ErrorKind::Api(Box::new(err))
+    }
+}
+
+impl From<ApiError> for Error {
+    fn from(err: ApiError) -> Error {
+        ErrorKind::from(err).into()
+    }
+}
+
+impl From<&Status> for ApiError {
+    fn from(status: &Status) -> ApiError {
+        let code = status.code.unwrap_or_default();
+        let reason = match status.reason {
+            Some(ref reason) if !reason.is_empty() => reason.clone(),
+            _ => reason_for_code(code).to_owned(),
+        };
+        ApiError {
+            code,
+            reason,
+            message: status.message.clone(),
+            details: status.details.clone(),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        let causes = self.details.as_ref().and_then(|d| d.causes.as_ref());
+        for cause in causes.into_iter().flat_map(|c| c.iter()) {
+            match (cause.field.as_ref(), cause.message.as_ref()) {
+                (Some(field), Some(message)) => write!(f, "; {}: {}", field, message)?,
+                (None, Some(message)) => write!(f, "; {}", message)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+// The `StatusReason` kubernetes would report for an HTTP status code
+fn reason_for_code(code: u16) -> &'static str {
+    match code {
+        400 => "BadRequest",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "NotFound",
+        405 => "MethodNotAllowed",
+        406 => "NotAcceptable",
+        409 => "Conflict",
+        410 => "Expired",
+        415 => "UnsupportedMediaType",
+        422 => "Invalid",
+        429 => "TooManyRequests",
+        500 => "InternalError",
+        503 => "ServiceUnavailable",
+        504 => "Timeout",
+        _ => "Unknown",
+    }
+}
+
+impl Error {
+    /// The API failure behind this error, if any (looking through chained errors)
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match *self.kind() {
+            ErrorKind::Api(ref err) | ErrorKind::ApplyConflict(ref err, _) => Some(&**err),
+            _ => self.1.next_error.as_ref()
+                .and_then(|next| next.downcast_ref::<Error>())
+                .and_then(Error::api_error),
+        }
+    }
+
+    /// The conflicts with other field managers behind a failed server-side apply
+    pub fn apply_conflicts(&self) -> Option<&[FieldManagerConflict]> {
+        match *self.kind() {
+            ErrorKind::ApplyConflict(_, ref conflicts) => Some(conflicts),
+            _ => self.1.next_error.as_ref()
+                .and_then(|next| next.downcast_ref::<Error>())
+                .and_then(Error::apply_conflicts),
+        }
+    }
+
+    /// Indicates that the server doesn't serve the kind of a typed resource,
+    /// see `Kubernetes::check_served`
+    pub fn is_not_served(&self) -> bool {
+        match *self.kind() {
+            ErrorKind::NotServed(..) => true,
+            _ => self.1.next_error.as_ref()
+                .and_then(|next| next.downcast_ref::<Error>())
+                .is_some_and(Error::is_not_served),
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.api_error().is_some_and(ApiError::is_not_found)
+    }
+
+    pub fn is_already_exists(&self) -> bool {
+        self.api_error().is_some_and(ApiError::is_already_exists)
+    }
+
+    pub fn is_conflict(&self) -> bool {
+        self.api_error().is_some_and(ApiError::is_conflict)
+    }
+
+    pub fn is_invalid(&self) -> bool {
+        self.api_error().is_some_and(ApiError::is_invalid)
+    }
+
+    pub fn is_forbidden(&self) -> bool {
+        self.api_error().is_some_and(ApiError::is_forbidden)
+    }
+
+    pub fn is_unauthorized(&self) -> bool {
+        self.api_error().is_some_and(ApiError::is_unauthorized)
+    }
+
+    pub fn is_gone(&self) -> bool {
+        self.api_error().is_some_and(ApiError::is_gone)
+    }
+
+    pub fn is_too_many_requests(&self) -> bool {
+        self.api_error().is_some_and(ApiError::is_too_many_requests)
+    }
+
+    pub fn is_server_timeout(&self) -> bool {
+        self.api_error().is_some_and(ApiError::is_server_timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_responses_are_decoded() {
+        let body = r#"{
+            "kind": "Status",
+            "apiVersion": "v1",
+            "status": "Failure",
+            "message": "pods \"web\" not found",
+            "reason": "NotFound",
+            "details": {"name": "web", "kind": "pods"},
+            "code": 404
+        }"#;
+        let err = ApiError::from_response_body(404, body);
+        assert_eq!(err.code, 404);
+        assert!(err.is_not_found());
+        assert_eq!(err.to_string(), "pods \"web\" not found");
+
+        let err: Error = ErrorKind::Api(Box::new(err)).into();
+        assert!(err.is_not_found());
+        assert!(!err.is_conflict());
+    }
+
+    #[test]
+    fn non_status_bodies_fall_back_to_the_http_code() {
+        let err = ApiError::from_response_body(502, "<html><body>Bad Gateway</body></html>");
+        assert_eq!(err.code, 502);
+        assert_eq!(err.reason, "Unknown");
+        assert_eq!(err.message, "the server responded with status 502 and an unexpected body: <html><body>Bad Gateway</body></html>");
+
+        let err = ApiError::from_response_body(503, "");
+        assert_eq!(err.reason, "ServiceUnavailable");
+        assert_eq!(err.message, "the server responded with status 503 and no body");
+    }
+
+    #[test]
+    fn statuses_without_reason_or_code_are_completed() {
+        let err = ApiError::from_response_body(409, r#"{"kind": "Status", "message": "conflict"}"#);
+        assert_eq!(err.code, 409);
+        assert!(err.is_conflict());
+
+        // e.g. the `ERROR` event of a watch whose resourceVersion is too old
+        let status = Status { code: Some(410), message: "too old resource version".to_owned(), ..Default::default() };
+        assert!(ApiError::from(&status).is_gone());
+    }
+
+    #[test]
+    fn validation_causes_are_displayed() {
+        let body = r#"{
+            "kind": "Status",
+            "message": "Deployment.apps \"web\" is invalid",
+            "reason": "Invalid",
+            "details": {"causes": [
+                {"field": "spec.replicas", "message": "must be greater than or equal to 0"},
+                {"message": "something else"}
+            ]},
+            "code": 422
+        }"#;
+        let err = ApiError::from_response_body(422, body);
+        assert!(err.is_invalid());
+        assert_eq!(err.to_string(), "Deployment.apps \"web\" is invalid; \
+            spec.replicas: must be greater than or equal to 0; something else");
+    }
+
+    #[test]
+    fn field_manager_conflicts_are_listed() {
+        let body = r#"{
+            "kind": "Status",
+            "message": "Apply failed with 1 conflict: conflict with \"kubectl\" using apps/v1: .spec.replicas",
+            "reason": "Conflict",
+            "details": {"causes": [
+                {"reason": "FieldManagerConflict", "message": "conflict with \"kubectl\" using apps/v1", "field": ".spec.replicas"},
+                {"reason": "FieldValueInvalid", "message": "unrelated", "field": ".spec.paused"}
+            ]},
+            "code": 409
+        }"#;
+        let err = ApiError::from_response_body(409, body);
+        assert_eq!(err.field_manager_conflicts(), vec![FieldManagerConflict {
+            manager: "kubectl".to_owned(),
+            field: ".spec.replicas".to_owned(),
+            message: "conflict with \"kubectl\" using apps/v1".to_owned(),
+        }]);
+    }
+
+    #[test]
+    fn api_errors_are_found_through_chained_errors() {
+        let status = Status { code: Some(403), ..Default::default() };
+        let err: Error = ErrorKind::Api(Box::new(ApiError::from(&status))).into();
+        let err = Error::with_chain(err, "Failed to list pods");
+        assert!(err.is_forbidden());
+        assert_eq!(err.api_error().map(|e| e.code), Some(403));
+    }
+
+    #[test]
+    fn unserved_kinds_are_found_through_chained_errors() {
+        let err: Error = ErrorKind::NotServed("Deployment".to_owned(), "extensions/v1beta1".to_owned(), Some("apps/v1".to_owned())).into();
+        assert_eq!(err.to_string(), "extensions/v1beta1 Deployment is not served by the Kubernetes API server, which serves apps/v1");
+        let err = Error::with_chain(err, "Failed to get deployment");
+        assert!(err.is_not_served());
+        assert!(!err.is_not_found());
+    }
 }
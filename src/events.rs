@@ -0,0 +1,239 @@
+//! Recording kubernetes `Events` about the resources a program acts on
+
+use chrono::Utc;
+use clients::Kubernetes;
+use errors::*;
+use k8s_api::api::core::v1::EventSource;
+use k8s_api::apimachinery::pkg::apis::meta::v1::{ObjectMeta, Time};
+use resources::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+// Bounds the memory used for aggregating events
+const MAX_CACHED_EVENTS: usize = 4096;
+
+/// The type of an `Event`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventType { Normal, Warning }
+
+// Debug output of EventType is exactly what we want for Display
+impl fmt::Display for EventType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+// Events are aggregated when they are about the same object and say the same thing
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct AggregateKey {
+    kind: Option<String>,
+    namespace: Option<String>,
+    name: Option<String>,
+    uid: Option<String>,
+    event_type: EventType,
+    reason: String,
+    message: String,
+}
+
+/// Emits `Events` attached to kubernetes resources
+///
+/// Repeated events (same object, type, reason and message) are aggregated client-side:
+/// instead of creating a new `Event`, the `count` and `lastTimestamp` of the previous
+/// one are bumped.
+///
+/// ## Examples
+///
+/// ```no_run
+/// # use qube::prelude::*;
+/// # use qube::events::EventRecorder;
+/// let kube = Kubernetes::load_conf("admin.conf")?;
+/// let recorder = EventRecorder::new(&kube, "my-controller");
+/// let deployment = kube.deployments().get("web")?;
+/// recorder.normal(&deployment, "Scaled", "Scaled deployment web to 5")?;
+/// # Ok::<(), qube::Error>(())
+/// ```
+pub struct EventRecorder {
+    kube: Kubernetes,
+    component: String,
+    host: Option<String>,
+    cache: Mutex<HashMap<AggregateKey, Event>>,
+}
+
+impl EventRecorder {
+    /// Creates a recorder reporting events as coming from `component`
+    pub fn new(kube: &Kubernetes, component: &str) -> EventRecorder {
+        EventRecorder {
+            kube: kube.clone(),
+            component: component.to_owned(),
+            host: None,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sets the host (e.g. node or pod name) reported as the source of the events
+    pub fn host(mut self, host: &str) -> EventRecorder {
+        self.host = Some(host.to_owned());
+        self
+    }
+
+    /// Records a `Normal` event about `resource`
+    pub fn normal<R: Resource>(&self, resource: &R, reason: &str, message: &str) -> Result<Event> {
+        self.record(resource, EventType::Normal, reason, message)
+    }
+
+    /// Records a `Warning` event about `resource`
+    pub fn warning<R: Resource>(&self, resource: &R, reason: &str, message: &str) -> Result<Event> {
+        self.record(resource, EventType::Warning, reason, message)
+    }
+
+    /// Records an event about `resource`
+    ///
+    /// `reason` should be a short, UpperCamelCase, machine understandable string
+    /// (e.g. `ScalingReplicaSet`), while `message` is meant for humans.
+    pub fn record<R: Resource>(&self, resource: &R, event_type: EventType, reason: &str, message: &str) -> Result<Event> {
+        let involved_object = resource.object_reference();
+        let key = AggregateKey {
+            kind: involved_object.kind.clone(),
+            namespace: involved_object.namespace.clone(),
+            name: involved_object.name.clone(),
+            uid: involved_object.uid.clone(),
+            event_type,
+            reason: reason.to_owned(),
+            message: message.to_owned(),
+        };
+        // Events of cluster-scoped resources go to the default namespace
+        let namespace = involved_object.namespace.clone().unwrap_or_else(|| "default".to_owned());
+        let kube = self.kube.namespace(&namespace);
+        let now = Time(Utc::now());
+
+        // The cache is not locked during requests, so that slow ones don't hold up other events
+        let cached = self.cache.lock().unwrap().remove(&key);
+        if let Some(mut event) = cached {
+            event.count = Some(event.count.unwrap_or(1) + 1);
+            event.last_timestamp = Some(now.clone());
+            match kube.update(&event) {
+                Ok(updated) => {
+                    self.remember(key, updated.clone());
+                    return Ok(updated);
+                }
+                // The previous event expired on the server, so start over
+                Err(ref err) if err.is_not_found() => {}
+                Err(err) => return Err(err)
+                    .chain_err(|| format!("Failed to update event {} in namespace {}", reason, namespace)),
+            }
+        }
+
+        let name = format!(
+            "{}.{:x}",
+            involved_object.name.as_deref().unwrap_or("unknown"),
+            now.0.timestamp_nanos_opt().unwrap_or_default(),
+        );
+        let event = Event {
+            types: TypeMeta::of::<Event>(),
+            metadata: ObjectMeta {
+                name: Some(name),
+                namespace: Some(namespace.clone()),
+                ..Default::default()
+            },
+            involved_object,
+            reason: Some(reason.to_owned()),
+            message: Some(message.to_owned()),
+            source: Some(EventSource {
+                component: Some(self.component.clone()),
+                host: self.host.clone(),
+            }),
+            first_timestamp: Some(now.clone()),
+            last_timestamp: Some(now),
+            count: Some(1),
+            type_: Some(event_type.to_string()),
+            reporting_component: Some(self.component.clone()),
+            reporting_instance: self.host.clone(),
+        };
+        let created = kube.create(&event)
+            .chain_err(|| format!("Failed to record event {} in namespace {}", reason, namespace))?;
+        self.remember(key, created.clone());
+        Ok(created)
+    }
+
+    fn remember(&self, key: AggregateKey, event: Event) {
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= MAX_CACHED_EVENTS {
+            cache.clear();
+        }
+        cache.insert(key, event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+    use fake_server::FakeServer;
+    use prelude::*;
+    use serde_json::json;
+
+    fn config_map() -> ConfigMap {
+        let mut config_map = ConfigMap::new("settings");
+        config_map.metadata_mut().namespace = Some("apps".to_owned());
+        config_map
+    }
+
+    fn path(event: &Event) -> String {
+        format!("/api/v1/namespaces/apps/events/{}", event.metadata.name.as_deref().unwrap())
+    }
+
+    fn last_timestamp(event: &Event) -> DateTime<Utc> {
+        event.last_timestamp.as_ref().unwrap().0
+    }
+
+    #[test]
+    fn repeated_events_are_aggregated() {
+        let server = FakeServer::start();
+        let recorder = EventRecorder::new(&server.kube(), "controller");
+
+        let first = recorder.normal(&config_map(), "Synced", "Synced settings").unwrap();
+        let second = recorder.normal(&config_map(), "Synced", "Synced settings").unwrap();
+        let third = recorder.normal(&config_map(), "Synced", "Synced settings").unwrap();
+        assert_eq!(third.metadata.name, first.metadata.name);
+        assert_eq!(third.count, Some(3));
+        assert_eq!(third.first_timestamp, first.first_timestamp);
+        assert!(last_timestamp(&third) >= last_timestamp(&second));
+        assert!(last_timestamp(&second) >= last_timestamp(&first));
+
+        let stored = server.object(&path(&first)).unwrap();
+        assert_eq!(stored["count"], 3);
+        assert_eq!(stored["involvedObject"]["name"], "settings");
+
+        let other = recorder.warning(&config_map(), "Synced", "Synced settings").unwrap();
+        assert_ne!(other.metadata.name, first.metadata.name);
+        assert_eq!(other.count, Some(1));
+    }
+
+    #[test]
+    fn expired_events_are_recreated() {
+        let server = FakeServer::start();
+        let kube = server.kube();
+        let recorder = EventRecorder::new(&kube, "controller");
+
+        let first = recorder.normal(&config_map(), "Synced", "Synced settings").unwrap();
+        kube.namespace("apps").events().delete(first.metadata.name.as_deref().unwrap()).unwrap();
+        let second = recorder.normal(&config_map(), "Synced", "Synced settings").unwrap();
+        assert_eq!(second.count, Some(1));
+        assert!(server.object(&path(&second)).is_some());
+    }
+
+    #[test]
+    fn failing_updates_are_returned() {
+        let server = FakeServer::start();
+        let recorder = EventRecorder::new(&server.kube(), "controller");
+        recorder.normal(&config_map(), "Synced", "Synced settings").unwrap();
+
+        server.on(|request| match request.method.as_str() {
+            "PUT" => Some((403, json!({ "kind": "Status", "status": "Failure", "reason": "Forbidden", "code": 403 }))),
+            _ => None,
+        });
+        let err = recorder.normal(&config_map(), "Synced", "Synced settings").unwrap_err();
+        assert!(err.is_forbidden());
+    }
+}
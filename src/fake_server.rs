@@ -0,0 +1,271 @@
+//! An in-memory Kubernetes API server for tests, served over plain HTTP on a local port
+//!
+//! It keeps objects by path and handles `GET`, `POST`, `PUT` and `DELETE` on them like the
+//! API server does, including `resourceVersion` conflicts and the `status` subresource.
+//! Tests can also answer chosen requests themselves with `FakeServer::on`.
+
+use clients::Kubernetes;
+use serde_json::{self, json, Value};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use url::Url;
+
+/// A request received by the server
+#[derive(Clone, Debug)]
+pub(crate) struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: BTreeMap<String, String>,
+    pub body: Value,
+}
+
+type Handler = dyn Fn(&Request) -> Option<(u16, Value)> + Send + Sync;
+
+#[derive(Default)]
+struct State {
+    objects: BTreeMap<String, Value>,
+    resource_version: u64,
+    requests: Vec<Request>,
+    handler: Option<Arc<Handler>>,
+}
+
+#[derive(Clone)]
+pub(crate) struct FakeServer {
+    port: u16,
+    state: Arc<Mutex<State>>,
+}
+
+impl FakeServer {
+    pub fn start() -> FakeServer {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server = FakeServer {
+            port: listener.local_addr().unwrap().port(),
+            state: Arc::new(Mutex::new(State::default())),
+        };
+        let state = server.state.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().filter_map(|stream| stream.ok()) {
+                let state = state.clone();
+                thread::spawn(move || serve(stream, &state));
+            }
+        });
+        server
+    }
+
+    /// A client of this server, whose namespace is `default`
+    pub fn kube(&self) -> Kubernetes {
+        let kubeconfig = format!(
+            "apiVersion: v1\nkind: Config\n\
+             clusters:\n- name: fake\n  cluster:\n    server: http://127.0.0.1:{}\n\
+             users:\n- name: fake\n  user:\n    token: fake-token\n\
+             contexts:\n- name: fake\n  context:\n    cluster: fake\n    user: fake\n\
+             current-context: fake\n",
+            self.port);
+        let path = env::temp_dir().join(format!("qube-fake-server-{}.yaml", self.port));
+        fs::write(&path, kubeconfig).unwrap();
+        Kubernetes::load_conf(&path).unwrap()
+    }
+
+    /// Answers the requests for which `handler` returns a status code and body,
+    /// the others are served from the stored objects
+    pub fn on<F>(&self, handler: F)
+    where F: Fn(&Request) -> Option<(u16, Value)> + Send + Sync + 'static
+    {
+        self.state.lock().unwrap().handler = Some(Arc::new(handler));
+    }
+
+    pub fn object(&self, path: &str) -> Option<Value> {
+        self.state.lock().unwrap().objects.get(path).cloned()
+    }
+
+    /// Every request received so far, in order
+    pub fn requests(&self) -> Vec<Request> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+fn serve(stream: TcpStream, state: &Mutex<State>) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let request = match read_request(&mut reader) {
+        Some(request) => request,
+        None => return,
+    };
+    let handler = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(request.clone());
+        state.handler.clone()
+    };
+    let (code, body) = match handler.and_then(|handler| handler(&request)) {
+        Some(response) => response,
+        None => respond(&mut state.lock().unwrap(), &request),
+    };
+    let body = serde_json::to_string(&body).unwrap();
+    let mut stream = stream;
+    let _ = write!(stream, "HTTP/1.1 {} Fake\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        code, body.len(), body);
+}
+
+fn read_request<R: BufRead>(reader: &mut R) -> Option<Request> {
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_owned();
+    let url = Url::parse(&format!("http://fake{}", parts.next()?)).ok()?;
+
+    let mut content_length = 0;
+    let mut chunked = false;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).ok()?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header.split_at(header.find(':')?);
+        let value = value[1..].trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.parse().ok()?;
+        } else if name.eq_ignore_ascii_case("transfer-encoding") && value.eq_ignore_ascii_case("chunked") {
+            chunked = true;
+        }
+    }
+
+    let mut body = Vec::new();
+    if chunked {
+        loop {
+            let mut size = String::new();
+            reader.read_line(&mut size).ok()?;
+            let size = usize::from_str_radix(size.trim(), 16).ok()?;
+            let mut chunk = vec![0; size + 2];
+            reader.read_exact(&mut chunk).ok()?;
+            if size == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..size]);
+        }
+    } else {
+        body.resize(content_length, 0);
+        reader.read_exact(&mut body).ok()?;
+    }
+
+    Some(Request {
+        method,
+        path: url.path().to_owned(),
+        query: url.query_pairs().into_owned().collect(),
+        body: serde_json::from_slice(&body).unwrap_or(Value::Null),
+    })
+}
+
+fn status(code: u16, reason: &str, message: &str) -> (u16, Value) {
+    (code, json!({
+        "kind": "Status",
+        "apiVersion": "v1",
+        "status": if code < 400 { "Success" } else { "Failure" },
+        "message": message,
+        "reason": reason,
+        "code": code,
+    }))
+}
+
+// What a path addresses: a collection, an object, or a subresource of an object
+enum Target<'a> {
+    Collection(&'a str),
+    Object(&'a str),
+    Subresource(&'a str, &'a str),
+}
+
+fn target(path: &str) -> Option<Target<'_>> {
+    let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+    let mut rest = match segments.first() {
+        Some(&"api") => segments.get(2..)?,
+        Some(&"apis") => segments.get(3..)?,
+        _ => return None,
+    };
+    if rest.len() >= 3 && rest[0] == "namespaces" {
+        rest = &rest[2..];
+    }
+    match rest.len() {
+        1 => Some(Target::Collection(path)),
+        2 => Some(Target::Object(path)),
+        3 => Some(Target::Subresource(&path[..path.len() - rest[2].len() - 1], rest[2])),
+        _ => None,
+    }
+}
+
+fn respond(state: &mut State, request: &Request) -> (u16, Value) {
+    let target = match target(&request.path) {
+        Some(target) => target,
+        None => return status(404, "NotFound", "unknown path"),
+    };
+    match (request.method.as_str(), target) {
+        ("GET", Target::Collection(path)) => {
+            let prefix = format!("{}/", path);
+            let items: Vec<Value> = state.objects.iter()
+                .filter(|&(key, _)| key.starts_with(&prefix) && !key[prefix.len()..].contains('/'))
+                .map(|(_, object)| object.clone())
+                .collect();
+            (200, json!({
+                "kind": "List",
+                "apiVersion": "v1",
+                "metadata": { "resourceVersion": state.resource_version.to_string() },
+                "items": items,
+            }))
+        }
+        ("GET", Target::Object(path)) | ("GET", Target::Subresource(path, "status")) => match state.objects.get(path) {
+            Some(object) => (200, object.clone()),
+            None => status(404, "NotFound", &format!("{} not found", path)),
+        },
+        ("POST", Target::Collection(path)) => {
+            let name = request.body["metadata"]["name"].as_str().unwrap_or_default();
+            let key = format!("{}/{}", path, name);
+            if state.objects.contains_key(&key) {
+                return status(409, "AlreadyExists", &format!("{} already exists", key));
+            }
+            let mut object = request.body.clone();
+            let segments: Vec<&str> = path.split('/').collect();
+            if let Some(i) = segments.iter().position(|&segment| segment == "namespaces") {
+                object["metadata"]["namespace"] = Value::from(segments[i + 1]);
+            }
+            (201, store(state, key, object))
+        }
+        ("PUT", Target::Object(path)) => replace(state, path, request.body.clone()),
+        ("PUT", Target::Subresource(path, "status")) => {
+            let mut object = match state.objects.get(path) {
+                Some(object) => object.clone(),
+                None => return status(404, "NotFound", &format!("{} not found", path)),
+            };
+            object["status"] = request.body["status"].clone();
+            object["metadata"]["resourceVersion"] = request.body["metadata"]["resourceVersion"].clone();
+            replace(state, path, object)
+        }
+        ("DELETE", Target::Object(path)) => match state.objects.remove(path) {
+            Some(_) => status(200, "", "deleted"),
+            None => status(404, "NotFound", &format!("{} not found", path)),
+        },
+        _ => status(405, "MethodNotAllowed", "not supported by the fake server"),
+    }
+}
+
+fn replace(state: &mut State, path: &str, object: Value) -> (u16, Value) {
+    let current = match state.objects.get(path) {
+        Some(current) => current["metadata"]["resourceVersion"].clone(),
+        None => return status(404, "NotFound", &format!("{} not found", path)),
+    };
+    let version = &object["metadata"]["resourceVersion"];
+    if !version.is_null() && *version != current {
+        return status(409, "Conflict", &format!("Operation cannot be fulfilled on {}: the object has been modified", path));
+    }
+    (200, store(state, path.to_owned(), object))
+}
+
+fn store(state: &mut State, key: String, mut object: Value) -> Value {
+    state.resource_version += 1;
+    object["metadata"]["resourceVersion"] = Value::from(state.resource_version.to_string());
+    state.objects.insert(key, object.clone());
+    object
+}
@@ -0,0 +1,462 @@
+//! Leader election between replicas of a controller
+//!
+//! Candidates compete for a [`ResourceLock`](trait.ResourceLock.html), i.e. a `ConfigMap`
+//! annotation or a `Lease`. The holder keeps renewing it, and the others take
+//! over once it was not renewed for a whole lease duration.
+//!
+//! ## Examples
+//!
+//! ```no_run
+//! # use qube::prelude::*;
+//! # use qube::leader_election::*;
+//! let kube = Kubernetes::load_conf("admin.conf")?;
+//! let lock = LeaseLock::new(&kube, "default", "my-controller");
+//! let elector = LeaderElector::new(lock, LeaderElectionConfig::new("replica-1"))?
+//!     .on_started_leading(|| println!("Leading"))
+//!     .on_stopped_leading(|| println!("Lost leadership"));
+//! elector.run();
+//! # Ok::<(), qube::Error>(())
+//! ```
+
+mod resource_lock;
+
+pub use self::resource_lock::*;
+
+use chrono::{DateTime, Utc};
+use errors::*;
+use rand::{thread_rng, Rng};
+use resources::LeaseSpec;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const JITTER_FACTOR: f64 = 1.2;
+const TICK_MILLIS: u64 = 100;
+
+/// The state of a leader election, as stored in its lock
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LeaderElectionRecord {
+    pub holder_identity: String,
+    pub lease_duration_seconds: i32,
+    pub acquire_time: Option<DateTime<Utc>>,
+    pub renew_time: Option<DateTime<Utc>>,
+    pub leader_transitions: i32,
+}
+
+impl From<&LeaseSpec> for LeaderElectionRecord {
+    fn from(spec: &LeaseSpec) -> LeaderElectionRecord {
+        LeaderElectionRecord {
+            holder_identity: spec.holder_identity.clone().unwrap_or_default(),
+            lease_duration_seconds: spec.lease_duration_seconds.unwrap_or_default(),
+            acquire_time: spec.acquire_time,
+            renew_time: spec.renew_time,
+            leader_transitions: spec.lease_transitions.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<&LeaderElectionRecord> for LeaseSpec {
+    fn from(record: &LeaderElectionRecord) -> LeaseSpec {
+        LeaseSpec {
+            holder_identity: Some(record.holder_identity.clone()),
+            lease_duration_seconds: Some(record.lease_duration_seconds),
+            acquire_time: record.acquire_time,
+            renew_time: record.renew_time,
+            lease_transitions: Some(record.leader_transitions),
+        }
+    }
+}
+
+/// Timings of a leader election
+#[derive(Clone, Debug)]
+pub struct LeaderElectionConfig {
+    /// The identity of this candidate, unique among all candidates
+    pub identity: String,
+
+    /// How long non-leaders wait after the last observed renewal before taking over
+    pub lease_duration: Duration,
+
+    /// How long the leader keeps retrying to renew before giving up leadership
+    pub renew_deadline: Duration,
+
+    /// How long candidates wait between attempts to acquire or renew
+    pub retry_period: Duration,
+
+    /// Whether to give up the lock when the elector is stopped, so that
+    /// another candidate can take over without waiting for the lease to expire
+    pub release_on_stop: bool,
+}
+
+impl LeaderElectionConfig {
+    /// Creates a config with the same defaults as kubernetes' own controllers
+    /// (15s lease duration, 10s renew deadline, 2s retry period)
+    pub fn new(identity: &str) -> LeaderElectionConfig {
+        LeaderElectionConfig {
+            identity: identity.to_owned(),
+            lease_duration: Duration::from_secs(15),
+            renew_deadline: Duration::from_secs(10),
+            retry_period: Duration::from_secs(2),
+            release_on_stop: false,
+        }
+    }
+}
+
+// The last seen record, and when it was first seen
+type Observation = (LeaderElectionRecord, Instant);
+type Callback = dyn Fn() + Send + Sync;
+type LeaderCallback = dyn Fn(&str) + Send + Sync;
+
+/// Competes for a `ResourceLock` and notifies callbacks when leadership changes
+///
+/// `run` blocks until leadership is lost or the elector is stopped, so an elector is
+/// usually shared through an `Arc` with whoever calls `stop`.
+pub struct LeaderElector<L> {
+    lock: L,
+    config: LeaderElectionConfig,
+    observed: Mutex<Option<Observation>>,
+    stopped: AtomicBool,
+    on_started_leading: Option<Arc<Callback>>,
+    on_stopped_leading: Option<Box<Callback>>,
+    on_new_leader: Option<Box<LeaderCallback>>,
+}
+
+impl<L: ResourceLock> LeaderElector<L> {
+    /// Creates an elector, validating that the timings of `config` are consistent
+    pub fn new(lock: L, config: LeaderElectionConfig) -> Result<LeaderElector<L>> {
+        if config.identity.is_empty() {
+            bail!("leader election identity must not be empty");
+        }
+        if config.lease_duration <= config.renew_deadline {
+            bail!("leader election lease duration must be greater than the renew deadline");
+        }
+        if config.renew_deadline.as_secs_f64() <= JITTER_FACTOR * config.retry_period.as_secs_f64() {
+            bail!("leader election renew deadline must be greater than {} times the retry period", JITTER_FACTOR);
+        }
+        Ok(LeaderElector {
+            lock,
+            config,
+            observed: Mutex::new(None),
+            stopped: AtomicBool::new(false),
+            on_started_leading: None,
+            on_stopped_leading: None,
+            on_new_leader: None,
+        })
+    }
+
+    /// Called in a new thread once this candidate became the leader
+    pub fn on_started_leading<F>(mut self, f: F) -> Self
+    where F: Fn() + Send + Sync + 'static
+    {
+        self.on_started_leading = Some(Arc::new(f));
+        self
+    }
+
+    /// Called when this candidate stops leading, either because it could not
+    /// renew the lock in time or because the elector was stopped
+    pub fn on_stopped_leading<F>(mut self, f: F) -> Self
+    where F: Fn() + Send + Sync + 'static
+    {
+        self.on_stopped_leading = Some(Box::new(f));
+        self
+    }
+
+    /// Called with the identity of the leader whenever a different leader is observed
+    pub fn on_new_leader<F>(mut self, f: F) -> Self
+    where F: Fn(&str) + Send + Sync + 'static
+    {
+        self.on_new_leader = Some(Box::new(f));
+        self
+    }
+
+    /// Acquires the lock, then keeps renewing it until leadership is lost
+    ///
+    /// Blocks until leadership is lost or `stop` is called.
+    pub fn run(&self) {
+        if !self.acquire() {
+            return;
+        }
+        if let Some(ref f) = self.on_started_leading {
+            let f = f.clone();
+            thread::spawn(move || f());
+        }
+        self.renew();
+        if self.config.release_on_stop && self.is_stopped() {
+            let _ = self.release();
+        }
+        if let Some(ref f) = self.on_stopped_leading {
+            f();
+        }
+    }
+
+    /// Makes `run` return at its next attempt to acquire or renew the lock
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+
+    /// Indicates whether this candidate was the leader when the lock was last observed
+    pub fn is_leader(&self) -> bool {
+        self.leader().as_ref() == Some(&self.config.identity)
+    }
+
+    /// The identity of the leader when the lock was last observed
+    pub fn leader(&self) -> Option<String> {
+        self.observed.lock().unwrap().as_ref()
+            .map(|(record, _)| record.holder_identity.clone())
+            .and_then(|holder| if holder.is_empty() { None } else { Some(holder) })
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+
+    // Retries until the lock is acquired, returns false if stopped first
+    fn acquire(&self) -> bool {
+        loop {
+            if self.is_stopped() {
+                return false;
+            }
+            if self.try_acquire_or_renew() {
+                return true;
+            }
+            self.sleep(self.jittered(self.config.retry_period));
+        }
+    }
+
+    // Keeps renewing until a renewal missed the deadline, or the elector was stopped
+    fn renew(&self) {
+        loop {
+            let deadline = Instant::now() + self.config.renew_deadline;
+            loop {
+                if self.is_stopped() {
+                    return;
+                }
+                if self.try_acquire_or_renew() {
+                    break;
+                }
+                if Instant::now() >= deadline {
+                    return;
+                }
+                self.sleep(self.config.retry_period);
+            }
+            self.sleep(self.config.retry_period);
+        }
+    }
+
+    fn try_acquire_or_renew(&self) -> bool {
+        let now = Utc::now();
+        let mut record = LeaderElectionRecord {
+            holder_identity: self.config.identity.clone(),
+            lease_duration_seconds: self.config.lease_duration.as_secs() as i32,
+            acquire_time: Some(now),
+            renew_time: Some(now),
+            leader_transitions: 0,
+        };
+
+        let (old, version) = match self.lock.get() {
+            Ok(Some(current)) => current,
+            Ok(None) => {
+                if self.lock.create(&record).is_err() {
+                    return false;
+                }
+                self.observe(record);
+                return true;
+            }
+            Err(_) => return false,
+        };
+
+        // The lease is measured against our own clock, from the moment we saw the
+        // record change, so candidates don't depend on synchronized clocks
+        let observed_at = self.observe(old.clone());
+        let held_by_other = !old.holder_identity.is_empty() && old.holder_identity != self.config.identity;
+        if held_by_other && observed_at.elapsed() < self.config.lease_duration {
+            return false;
+        }
+
+        if old.holder_identity == self.config.identity {
+            record.acquire_time = old.acquire_time;
+            record.leader_transitions = old.leader_transitions;
+        } else {
+            record.leader_transitions = old.leader_transitions + 1;
+        }
+
+        if self.lock.update(&record, &version).is_err() {
+            return false;
+        }
+        self.observe(record);
+        true
+    }
+
+    // Gives up the lock by clearing its holder
+    fn release(&self) -> Result<()> {
+        let (old, version) = match self.lock.get()? {
+            Some(current) => current,
+            None => return Ok(()),
+        };
+        if old.holder_identity != self.config.identity {
+            return Ok(());
+        }
+        let record = LeaderElectionRecord {
+            holder_identity: String::new(),
+            lease_duration_seconds: 1,
+            leader_transitions: old.leader_transitions,
+            ..Default::default()
+        };
+        self.lock.update(&record, &version)
+            .chain_err(|| format!("Failed to release {}", self.lock.describe()))?;
+        self.observe(record);
+        Ok(())
+    }
+
+    // Records the last seen state of the lock, returning when it was first seen
+    fn observe(&self, record: LeaderElectionRecord) -> Instant {
+        let mut observed = self.observed.lock().unwrap();
+        if let Some((ref current, at)) = *observed {
+            if *current == record {
+                return at;
+            }
+        }
+
+        let previous_leader = observed.as_ref().map(|(r, _)| r.holder_identity.clone());
+        let now = Instant::now();
+        let new_leader = record.holder_identity.clone();
+        *observed = Some((record, now));
+        drop(observed);
+
+        if !new_leader.is_empty() && previous_leader.as_ref() != Some(&new_leader) {
+            if let Some(ref f) = self.on_new_leader {
+                f(&new_leader);
+            }
+        }
+        now
+    }
+
+    fn jittered(&self, duration: Duration) -> Duration {
+        let jitter = thread_rng().gen_range(0.0, JITTER_FACTOR - 1.0);
+        duration + Duration::from_secs_f64(duration.as_secs_f64() * jitter)
+    }
+
+    // Sleeps in short ticks so that `stop` is noticed promptly
+    fn sleep(&self, duration: Duration) {
+        let start = Instant::now();
+        while !self.is_stopped() && start.elapsed() < duration {
+            thread::sleep(Duration::from_millis(TICK_MILLIS));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    fn config(identity: &str, lease_millis: u64) -> LeaderElectionConfig {
+        LeaderElectionConfig {
+            lease_duration: Duration::from_millis(lease_millis),
+            renew_deadline: Duration::from_millis(300),
+            retry_period: Duration::from_millis(100),
+            ..LeaderElectionConfig::new(identity)
+        }
+    }
+
+    fn holder(lock: &InMemoryLock) -> String {
+        lock.get().unwrap().unwrap().0.holder_identity
+    }
+
+    #[test]
+    fn inconsistent_timings_are_rejected() {
+        assert!(LeaderElector::new(InMemoryLock::new(), LeaderElectionConfig::new("")).is_err());
+        assert!(LeaderElector::new(InMemoryLock::new(), config("a", 300)).is_err());
+        assert!(LeaderElector::new(InMemoryLock::new(), LeaderElectionConfig::new("a")).is_ok());
+    }
+
+    #[test]
+    fn only_one_candidate_leads() {
+        let lock = InMemoryLock::new();
+        let a = LeaderElector::new(lock.clone(), config("a", 10_000)).unwrap();
+        let b = LeaderElector::new(lock.clone(), config("b", 10_000)).unwrap();
+
+        assert!(a.try_acquire_or_renew());
+        assert!(!b.try_acquire_or_renew());
+        assert!(a.try_acquire_or_renew());
+        assert!(!b.try_acquire_or_renew());
+
+        assert!(a.is_leader());
+        assert!(!b.is_leader());
+        assert_eq!(b.leader().as_deref(), Some("a"));
+        assert_eq!(holder(&lock), "a");
+    }
+
+    #[test]
+    fn candidates_take_over_once_the_lease_expired() {
+        let lock = InMemoryLock::new();
+        let a = LeaderElector::new(lock.clone(), config("a", 500)).unwrap();
+        let b = LeaderElector::new(lock.clone(), config("b", 500)).unwrap();
+
+        assert!(a.try_acquire_or_renew());
+        assert!(!b.try_acquire_or_renew());
+        // `a` stops renewing, so `b` sees the record unchanged for a whole lease
+        thread::sleep(Duration::from_millis(600));
+        assert!(b.try_acquire_or_renew());
+
+        let (record, _) = lock.get().unwrap().unwrap();
+        assert_eq!(record.holder_identity, "b");
+        assert_eq!(record.leader_transitions, 1);
+        assert!(!a.try_acquire_or_renew());
+        assert_eq!(a.leader().as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn failing_to_renew_stops_leading() {
+        let lock = InMemoryLock::new();
+        let started = Arc::new(AtomicUsize::new(0));
+        let stopped = Arc::new(AtomicUsize::new(0));
+        let elector = {
+            let (started, stopped) = (started.clone(), stopped.clone());
+            Arc::new(LeaderElector::new(lock.clone(), config("a", 10_000)).unwrap()
+                .on_started_leading(move || { started.fetch_add(1, Ordering::SeqCst); })
+                .on_stopped_leading(move || { stopped.fetch_add(1, Ordering::SeqCst); }))
+        };
+        let runner = {
+            let elector = elector.clone();
+            thread::spawn(move || elector.run())
+        };
+
+        let start = Instant::now();
+        while !elector.is_leader() {
+            assert!(start.elapsed() < Duration::from_secs(5), "never acquired the lock");
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // Another candidate overwrites the lock, so renewing fails until the deadline
+        let (mut record, version) = lock.get().unwrap().unwrap();
+        record.holder_identity = "b".to_owned();
+        lock.update(&record, &version).unwrap();
+
+        runner.join().unwrap();
+        assert_eq!(started.load(Ordering::SeqCst), 1);
+        assert_eq!(stopped.load(Ordering::SeqCst), 1);
+        assert!(!elector.is_leader());
+        assert_eq!(holder(&lock), "b");
+    }
+
+    #[test]
+    fn stopping_releases_the_lock_when_configured() {
+        let lock = InMemoryLock::new();
+        let elector = Arc::new(LeaderElector::new(lock.clone(), LeaderElectionConfig {
+            release_on_stop: true,
+            ..config("a", 10_000)
+        }).unwrap());
+        let runner = {
+            let elector = elector.clone();
+            thread::spawn(move || elector.run())
+        };
+        while !elector.is_leader() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        elector.stop();
+        runner.join().unwrap();
+        assert_eq!(holder(&lock), "");
+    }
+}
@@ -0,0 +1,267 @@
+use super::LeaderElectionRecord;
+use clients::Kubernetes;
+use errors::*;
+use resources::*;
+use serde_json;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// The annotation holding the `LeaderElectionRecord` of a `ConfigMapLock`
+pub const LEADER_ANNOTATION: &str = "control-plane.alpha.kubernetes.io/leader";
+
+/// An object in the cluster used as the lock of a leader election
+///
+/// Every lock has a version (the `resourceVersion` of kubernetes objects), and `update`
+/// only succeeds when given the version that was last read, so that two candidates
+/// can never both believe they acquired the lock.
+pub trait ResourceLock: Send + Sync {
+    /// Reads the current record and its version, or `None` if the lock doesn't exist yet
+    fn get(&self) -> Result<Option<(LeaderElectionRecord, String)>>;
+
+    /// Creates the lock holding `record`
+    fn create(&self, record: &LeaderElectionRecord) -> Result<()>;
+
+    /// Replaces the record, failing if the lock changed since `version` was read
+    fn update(&self, record: &LeaderElectionRecord, version: &str) -> Result<()>;
+
+    /// Describes the lock for error messages, e.g. `configmaps/default/my-controller`
+    fn describe(&self) -> String;
+}
+
+/// A lock stored in an annotation of a `ConfigMap`
+pub struct ConfigMapLock {
+    kube: Kubernetes,
+    namespace: String,
+    name: String,
+    config_map: Mutex<Option<ConfigMap>>,
+}
+
+impl ConfigMapLock {
+    pub fn new(kube: &Kubernetes, namespace: &str, name: &str) -> ConfigMapLock {
+        ConfigMapLock {
+            kube: kube.namespace(namespace),
+            namespace: namespace.to_owned(),
+            name: name.to_owned(),
+            config_map: Mutex::new(None),
+        }
+    }
+}
+
+impl ResourceLock for ConfigMapLock {
+    fn get(&self) -> Result<Option<(LeaderElectionRecord, String)>> {
+        let config_map: ConfigMap = match self.kube.get(&self.name) {
+            Ok(config_map) => config_map,
+            Err(ref err) if err.is_not_found() => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let metadata = config_map.metadata();
+        let record = match metadata.annotations.as_ref().and_then(|a| a.get(LEADER_ANNOTATION)) {
+            Some(record) => serde_json::from_str(record)
+                .chain_err(|| format!("Failed to decode leader election record of {}", self.describe()))?,
+            None => LeaderElectionRecord::default(),
+        };
+        let version = metadata.resource_version.clone().unwrap_or_default();
+        *self.config_map.lock().unwrap() = Some(config_map);
+        Ok(Some((record, version)))
+    }
+
+    fn create(&self, record: &LeaderElectionRecord) -> Result<()> {
+        let mut config_map = ConfigMap::new(&self.name);
+        set_annotation(&mut config_map, record)?;
+        let created = self.kube.create(&config_map)?;
+        *self.config_map.lock().unwrap() = Some(created);
+        Ok(())
+    }
+
+    fn update(&self, record: &LeaderElectionRecord, version: &str) -> Result<()> {
+        let mut guard = self.config_map.lock().unwrap();
+        let mut config_map = guard.take()
+            .chain_err(|| format!("{} must be read before it is updated", self.describe()))?;
+        config_map.metadata_mut().resource_version = Some(version.to_owned());
+        set_annotation(&mut config_map, record)?;
+        *guard = Some(self.kube.update(&config_map)?);
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        format!("configmaps/{}/{}", self.namespace, self.name)
+    }
+}
+
+fn set_annotation(config_map: &mut ConfigMap, record: &LeaderElectionRecord) -> Result<()> {
+    let record = serde_json::to_string(record)?;
+    config_map.metadata_mut().annotations
+        .get_or_insert_with(BTreeMap::new)
+        .insert(LEADER_ANNOTATION.to_owned(), record);
+    Ok(())
+}
+
+/// A lock stored in the spec of a `coordination.k8s.io` `Lease`
+///
+/// Leases need Kubernetes 1.12 or newer, see [`Lease`](../resources/struct.Lease.html).
+pub struct LeaseLock {
+    kube: Kubernetes,
+    namespace: String,
+    name: String,
+    lease: Mutex<Option<Lease>>,
+}
+
+impl LeaseLock {
+    pub fn new(kube: &Kubernetes, namespace: &str, name: &str) -> LeaseLock {
+        LeaseLock {
+            kube: kube.namespace(namespace),
+            namespace: namespace.to_owned(),
+            name: name.to_owned(),
+            lease: Mutex::new(None),
+        }
+    }
+}
+
+impl ResourceLock for LeaseLock {
+    fn get(&self) -> Result<Option<(LeaderElectionRecord, String)>> {
+        let lease: Lease = match self.kube.get(&self.name) {
+            Ok(lease) => lease,
+            Err(ref err) if err.is_not_found() => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let record = LeaderElectionRecord::from(&lease.spec);
+        let version = lease.metadata.resource_version.clone().unwrap_or_default();
+        *self.lease.lock().unwrap() = Some(lease);
+        Ok(Some((record, version)))
+    }
+
+    fn create(&self, record: &LeaderElectionRecord) -> Result<()> {
+        let mut lease = Lease::new(&self.name);
+        lease.spec = LeaseSpec::from(record);
+        let created = self.kube.create(&lease)?;
+        *self.lease.lock().unwrap() = Some(created);
+        Ok(())
+    }
+
+    fn update(&self, record: &LeaderElectionRecord, version: &str) -> Result<()> {
+        let mut guard = self.lease.lock().unwrap();
+        let mut lease = guard.take()
+            .chain_err(|| format!("{} must be read before it is updated", self.describe()))?;
+        lease.metadata.resource_version = Some(version.to_owned());
+        lease.spec = LeaseSpec::from(record);
+        *guard = Some(self.kube.update(&lease)?);
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        format!("leases/{}/{}", self.namespace, self.name)
+    }
+}
+
+/// A lock kept in memory, behaving like a lock object on an API server
+///
+/// Clones share the same lock, so several `LeaderElector`s can compete for it.
+/// Useful for testing leader election without a cluster.
+#[derive(Clone, Default)]
+pub struct InMemoryLock {
+    state: Arc<Mutex<Option<(LeaderElectionRecord, u64)>>>,
+}
+
+impl InMemoryLock {
+    pub fn new() -> InMemoryLock {
+        InMemoryLock::default()
+    }
+}
+
+impl ResourceLock for InMemoryLock {
+    fn get(&self) -> Result<Option<(LeaderElectionRecord, String)>> {
+        let state = self.state.lock().unwrap();
+        Ok(state.as_ref().map(|&(ref record, version)| (record.clone(), version.to_string())))
+    }
+
+    fn create(&self, record: &LeaderElectionRecord) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.is_some() {
+            bail!("{} already exists", self.describe());
+        }
+        *state = Some((record.clone(), 1));
+        Ok(())
+    }
+
+    fn update(&self, record: &LeaderElectionRecord, version: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let current = match *state {
+            Some((_, current)) => current,
+            None => bail!("{} not found", self.describe()),
+        };
+        if current.to_string() != version {
+            bail!("Operation cannot be fulfilled on {}: the object has been modified", self.describe());
+        }
+        *state = Some((record.clone(), current + 1));
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        "in-memory lock".to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake_server::FakeServer;
+
+    fn record(holder: &str, transitions: i32) -> LeaderElectionRecord {
+        LeaderElectionRecord {
+            holder_identity: holder.to_owned(),
+            lease_duration_seconds: 15,
+            leader_transitions: transitions,
+            ..Default::default()
+        }
+    }
+
+    // Creates the lock, updates it with the version read, then again with that stale version
+    fn check_lock<L: ResourceLock>(lock: &L) {
+        assert!(lock.get().unwrap().is_none());
+        lock.create(&record("a", 0)).unwrap();
+        assert!(lock.create(&record("b", 0)).unwrap_err().is_already_exists());
+
+        let (current, version) = lock.get().unwrap().unwrap();
+        assert_eq!(current, record("a", 0));
+        lock.update(&record("b", 1), &version).unwrap();
+
+        let (current, latest) = lock.get().unwrap().unwrap();
+        assert_eq!(current, record("b", 1));
+        assert_ne!(latest, version);
+        assert!(lock.update(&record("a", 2), &version).unwrap_err().is_conflict());
+        assert_eq!(lock.get().unwrap().unwrap().0, record("b", 1));
+    }
+
+    #[test]
+    fn config_map_lock() {
+        let server = FakeServer::start();
+        let lock = ConfigMapLock::new(&server.kube(), "kube-system", "controller");
+        check_lock(&lock);
+
+        let config_map = server.object("/api/v1/namespaces/kube-system/configmaps/controller").unwrap();
+        let stored = config_map["metadata"]["annotations"][LEADER_ANNOTATION].as_str().unwrap();
+        assert_eq!(serde_json::from_str::<LeaderElectionRecord>(stored).unwrap(), record("b", 1));
+    }
+
+    #[test]
+    fn lease_lock() {
+        let server = FakeServer::start();
+        let lock = LeaseLock::new(&server.kube(), "kube-system", "controller");
+        check_lock(&lock);
+
+        let path = format!("/apis/coordination.k8s.io/{}/namespaces/kube-system/leases/controller", Lease::VERSION);
+        let lease = server.object(&path).unwrap();
+        assert_eq!(lease["spec"]["holderIdentity"], "b");
+        assert_eq!(lease["spec"]["leaseTransitions"], 1);
+    }
+
+    #[test]
+    fn errors_other_than_not_found_are_returned() {
+        let server = FakeServer::start();
+        server.on(|_| Some((403, serde_json::json!({
+            "kind": "Status", "status": "Failure", "reason": "Forbidden", "code": 403,
+        }))));
+        let lock = ConfigMapLock::new(&server.kube(), "kube-system", "controller");
+        assert!(lock.get().unwrap_err().is_forbidden());
+    }
+}
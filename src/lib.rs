@@ -4,6 +4,25 @@
 //! So expect to file issues and PRs to unblock yourself if you actually
 //! take this crate as a dependency.
 //!
+//! ## API versions
+//!
+//! The resource types follow the Kubernetes API version selected by a cargo feature,
+//! from `v1_9` (the default) to `v1_17`. Exactly one of them must be enabled, so the default
+//! features are disabled to select another version:
+//!
+//! ```toml
+//! [dependencies]
+//! qube = { version = "0.4", default-features = false, features = ["v1_17"] }
+//! ```
+//!
+//! Kinds that moved between API groups are served from the group of the selected version.
+//! Workloads go through `apps/v1` and network policies through `networking.k8s.io/v1`,
+//! which every supported version serves.
+//!
+//! `v1_17` is the newest version of `k8s-openapi` 0.7, the last release of the types
+//! qube is built on that still supports Kubernetes 1.9. Newer clusters still serve
+//! these kinds, but fields added since are unknown.
+//!
 //! ## Basic Usage
 //!
 //! The `prelude` contains several the main [`Kubernetes`](clients/struct.Kubernetes.html) type
@@ -50,6 +69,15 @@ pub mod errors;
 pub mod config;
 pub mod clients;
 pub mod resources;
+pub mod cache;
+pub mod controller;
+pub mod leader_election;
+pub mod events;
+pub mod discovery;
+pub mod schema;
+
+#[cfg(test)]
+mod fake_server;
 
 pub mod prelude {
     pub use clients::{Kubernetes, ReadClient, WriteClient, ListClient};
@@ -59,4 +87,11 @@ pub use clients::Kubernetes;
 pub use config::KubeConfig;
 pub use errors::Error;
 
-use k8s_openapi::v1_9 as k8s_api;
+// The types of the API version selected by the `v1_*` feature, which k8s-openapi is built with
+use k8s_openapi as k8s_api;
+
+#[cfg(not(any(
+    feature = "v1_9", feature = "v1_10", feature = "v1_11", feature = "v1_12", feature = "v1_13",
+    feature = "v1_14", feature = "v1_15", feature = "v1_16", feature = "v1_17",
+)))]
+compile_error!("qube needs a Kubernetes API version, enable one of the `v1_9` to `v1_17` features");
@@ -2,15 +2,13 @@ use super::*;
 use std::collections::BTreeMap;
 use k8s_api::apimachinery::pkg::apis::meta::v1::ObjectMeta;
 
-pub(crate) static CONFIG_MAP_INFO: KindInfo = KindInfo {
-    plural: "configmaps",
-    default_namespace: Some("default"),
-    api: V1_API,
-};
-
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ConfigMap {
+    #[serde(flatten)]
+    pub types: TypeMeta,
+
     /// Data contains the configuration data. Each key must consist of alphanumeric characters, '-', '_' or '.'.
+    #[serde(default)]
     data: BTreeMap<String, String>,
 
     /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/api-conventions.md#metadata
@@ -21,7 +19,7 @@ impl ConfigMap {
     pub fn new(name: &str) -> ConfigMap {
         let data = BTreeMap::new();
         let metadata = ObjectMeta{ name: Some(name.to_owned()), ..Default::default() };
-        ConfigMap { data, metadata }
+        ConfigMap { types: TypeMeta::of::<ConfigMap>(), data, metadata }
     }
 
     pub fn insert<K, V>(&mut self, name: K, data: V) -> &mut ConfigMap
@@ -46,5 +44,34 @@ impl ConfigMap {
 }
 
 impl Resource for ConfigMap {
-    fn kind() -> Kind { Kind::ConfigMap }
+    const GROUP: &'static str = "";
+    const VERSION: &'static str = "v1";
+    const KIND: &'static str = "ConfigMap";
+    const PLURAL: &'static str = "configmaps";
+    const NAMESPACED: bool = true;
+    fn metadata(&self) -> &ObjectMeta { &self.metadata }
+    fn metadata_mut(&mut self) -> &mut ObjectMeta { &mut self.metadata }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn config_maps_without_data_decode() {
+        // The API server drops empty `data` maps, e.g. of the lock of a leader election
+        let json = r#"{
+            "apiVersion": "v1",
+            "kind": "ConfigMap",
+            "metadata": {
+                "name": "my-controller",
+                "namespace": "default",
+                "annotations": {"control-plane.alpha.kubernetes.io/leader": "{}"}
+            }
+        }"#;
+        let config_map: ConfigMap = serde_json::from_str(json).unwrap();
+        assert!(config_map.data.is_empty());
+        assert_eq!(config_map.metadata.name.as_deref(), Some("my-controller"));
+    }
 }
@@ -0,0 +1,238 @@
+use super::*;
+use k8s_api::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use schema::Schema;
+use serde_json::{Map, Value};
+
+/// A `CustomResourceDefinition` of `apiextensions.k8s.io/v1`, which installs a custom resource kind
+///
+/// The schema is kept as a raw `JSONSchemaProps` object, see the `schema` module.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct CustomResourceDefinition {
+    #[serde(flatten)]
+    pub types: TypeMeta,
+
+    /// Specification of the kind, its versions and their schemas
+    pub spec: CustomResourceDefinitionSpec,
+
+    /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/api-conventions.md#metadata
+    pub metadata: ObjectMeta,
+
+    /// Whether the kind is established, i.e. served by the API server
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<CustomResourceDefinitionStatus>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomResourceDefinitionSpec {
+    /// The API group, e.g. `example.com`
+    pub group: String,
+
+    pub names: CustomResourceDefinitionNames,
+
+    /// Either `Namespaced` or `Cluster`
+    pub scope: String,
+
+    pub versions: Vec<CustomResourceDefinitionVersion>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomResourceDefinitionNames {
+    pub kind: String,
+
+    pub plural: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub singular: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_kind: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub short_names: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomResourceDefinitionVersion {
+    /// The version, e.g. `v1`
+    pub name: String,
+
+    pub served: bool,
+
+    /// Whether resources are stored in this version, true of exactly one version
+    pub storage: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<CustomResourceValidation>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subresources: Option<CustomResourceSubresources>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomResourceValidation {
+    #[serde(rename = "openAPIV3Schema")]
+    pub open_api_v3_schema: Schema,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomResourceSubresources {
+    /// Enables the `/status` subresource when set, to an empty object
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<Value>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<CustomResourceSubresourceScale>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomResourceSubresourceScale {
+    /// JSON path of the desired replicas, e.g. `.spec.replicas`
+    pub spec_replicas_path: String,
+
+    /// JSON path of the observed replicas, e.g. `.status.replicas`
+    pub status_replicas_path: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label_selector_path: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomResourceDefinitionStatus {
+    #[serde(default)]
+    pub conditions: Vec<CustomResourceDefinitionCondition>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomResourceDefinitionCondition {
+    /// e.g. `Established` or `NamesAccepted`
+    #[serde(rename = "type")]
+    pub type_: String,
+
+    /// `True`, `False` or `Unknown`
+    pub status: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct CustomResourceDefinitionList {
+    items: Vec<CustomResourceDefinition>,
+}
+
+impl CustomResourceDefinition {
+    /// The CRD of a resource kind, with the schema of its `spec`
+    ///
+    /// The name is `<plural>.<group>`, as Kubernetes requires.
+    pub fn for_resource<R: Resource>(spec_schema: Schema) -> CustomResourceDefinition {
+        let mut properties = Map::new();
+        properties.insert("spec".to_owned(), spec_schema);
+        let mut schema = Map::new();
+        schema.insert("type".to_owned(), Value::from("object"));
+        schema.insert("properties".to_owned(), Value::Object(properties));
+        schema.insert("required".to_owned(), Value::from(vec!["spec"]));
+        let schema = Value::Object(schema);
+
+        let metadata = ObjectMeta {
+            name: Some(format!("{}.{}", R::PLURAL, R::GROUP)),
+            ..Default::default()
+        };
+        let spec = CustomResourceDefinitionSpec {
+            group: R::GROUP.to_owned(),
+            names: CustomResourceDefinitionNames {
+                kind: R::KIND.to_owned(),
+                plural: R::PLURAL.to_owned(),
+                singular: Some(R::KIND.to_lowercase()),
+                list_kind: Some(format!("{}List", R::KIND)),
+                short_names: Vec::new(),
+            },
+            scope: if R::NAMESPACED { "Namespaced" } else { "Cluster" }.to_owned(),
+            versions: vec![CustomResourceDefinitionVersion {
+                name: R::VERSION.to_owned(),
+                served: true,
+                storage: true,
+                schema: Some(CustomResourceValidation { open_api_v3_schema: schema }),
+                subresources: None,
+            }],
+        };
+        CustomResourceDefinition { types: TypeMeta::of::<Self>(), spec, metadata, status: None }
+    }
+
+    /// Adds the schema of the `status`, served through the `/status` subresource
+    pub fn with_status(mut self, status_schema: Schema) -> Self {
+        for version in &mut self.spec.versions {
+            if let Some(ref mut validation) = version.schema {
+                validation.open_api_v3_schema["properties"]["status"] = status_schema.clone();
+            }
+            version.subresources.get_or_insert_with(Default::default).status = Some(Value::Object(Map::new()));
+        }
+        self
+    }
+
+    /// Adds the `/scale` subresource, scaling the field at `spec_replicas_path` (e.g. `.spec.replicas`)
+    /// and observing the field at `status_replicas_path` (e.g. `.status.replicas`)
+    pub fn with_scale(mut self, spec_replicas_path: &str, status_replicas_path: &str) -> Self {
+        for version in &mut self.spec.versions {
+            version.subresources.get_or_insert_with(Default::default).scale = Some(CustomResourceSubresourceScale {
+                spec_replicas_path: spec_replicas_path.to_owned(),
+                status_replicas_path: status_replicas_path.to_owned(),
+                label_selector_path: None,
+            });
+        }
+        self
+    }
+
+    /// Whether the API server established the kind, i.e. serves it
+    pub fn is_established(&self) -> bool {
+        self.condition("Established").is_some_and(|c| c.status == "True")
+    }
+
+    pub fn condition(&self, type_: &str) -> Option<&CustomResourceDefinitionCondition> {
+        self.status.as_ref()?.conditions.iter().find(|c| c.type_ == type_)
+    }
+}
+
+impl Resource for CustomResourceDefinition {
+    const GROUP: &'static str = "apiextensions.k8s.io";
+    const VERSION: &'static str = "v1";
+    const KIND: &'static str = "CustomResourceDefinition";
+    const PLURAL: &'static str = "customresourcedefinitions";
+    const NAMESPACED: bool = false;
+    fn metadata(&self) -> &ObjectMeta { &self.metadata }
+    fn metadata_mut(&mut self) -> &mut ObjectMeta { &mut self.metadata }
+}
+
+impl HasStatus for CustomResourceDefinition {
+    type Status = CustomResourceDefinitionStatus;
+    fn status(&self) -> Option<&Self::Status> { self.status.as_ref() }
+    fn status_mut(&mut self) -> &mut Option<Self::Status> { &mut self.status }
+}
+
+impl ListableResource for CustomResourceDefinition {
+    type ListResponse = CustomResourceDefinitionList;
+    fn list_items(response: Self::ListResponse) -> Vec<Self> {
+        response.items
+    }
+    fn types_mut(&mut self) -> &mut TypeMeta { &mut self.types }
+}
+
+/// A custom resource kind whose CRD can be generated, see `Kubernetes::ensure_crd`
+///
+/// The CRD can also be written out as a manifest, e.g. with `serde_yaml::to_string(&Backup::crd())`.
+///
+/// `#[derive(CustomResource)]` implements it from the `JsonSchema` of the spec
+/// (and status).
+pub trait CustomResourceExt: Resource {
+    fn crd() -> CustomResourceDefinition;
+}
@@ -1,15 +1,12 @@
 use super::*;
-use ::k8s_api::api::apps::v1beta2::{DaemonSetSpec, DaemonSetStatus};
+use k8s_api::api::apps::v1::{DaemonSetSpec, DaemonSetStatus};
 use k8s_api::apimachinery::pkg::apis::meta::v1::ObjectMeta;
 
-pub(crate) static DAEMON_SET_INFO: KindInfo = KindInfo {
-    plural: "daemonsets",
-    default_namespace: Some("default"),
-    api: V1_BETA_API,
-};
-
 #[derive(Serialize, Deserialize, Debug)]
 pub struct DaemonSet {
+    #[serde(flatten)]
+    pub types: TypeMeta,
+
     /// The desired behavior of this daemon set. More info: https://git.k8s.io/community/contributors/devel/api-conventions.md#spec-and-status
     pub spec: DaemonSetSpec,
 
@@ -30,12 +27,24 @@ impl DaemonSet {
     pub fn new(name: &str) -> DaemonSet {
         let spec = DaemonSetSpec::default();
         let metadata = ObjectMeta{ name: Some(name.to_owned()), ..Default::default() };
-        DaemonSet { spec, metadata, status: None }
+        DaemonSet { types: TypeMeta::of::<DaemonSet>(), spec, metadata, status: None }
     }
 }
 
 impl Resource for DaemonSet {
-    fn kind() -> Kind { Kind::DaemonSet }
+    const GROUP: &'static str = "apps";
+    const VERSION: &'static str = "v1";
+    const KIND: &'static str = "DaemonSet";
+    const PLURAL: &'static str = "daemonsets";
+    const NAMESPACED: bool = true;
+    fn metadata(&self) -> &ObjectMeta { &self.metadata }
+    fn metadata_mut(&mut self) -> &mut ObjectMeta { &mut self.metadata }
+}
+
+impl HasStatus for DaemonSet {
+    type Status = DaemonSetStatus;
+    fn status(&self) -> Option<&Self::Status> { self.status.as_ref() }
+    fn status_mut(&mut self) -> &mut Option<Self::Status> { &mut self.status }
 }
 
 impl ListableResource for DaemonSet {
@@ -43,4 +52,5 @@ impl ListableResource for DaemonSet {
     fn list_items(response: Self::ListResponse) -> Vec<Self> {
         response.items
     }
+    fn types_mut(&mut self) -> &mut TypeMeta { &mut self.types }
 }
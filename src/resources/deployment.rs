@@ -1,16 +1,13 @@
 use super::*;
 use k8s_api::api::apps::v1::{DeploymentSpec, DeploymentStatus};
-use k8s_api::api::apps::v1beta1::{ScaleSpec, ScaleStatus};
+use k8s_api::api::autoscaling::v1::{ScaleSpec, ScaleStatus};
 use k8s_api::apimachinery::pkg::apis::meta::v1::ObjectMeta;
 
-pub(crate) static DEPLOYMENT_INFO: KindInfo = KindInfo {
-    plural: "deployments",
-    default_namespace: Some("default"),
-    api: V1_BETA_API,
-};
-
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Deployment {
+    #[serde(flatten)]
+    pub types: TypeMeta,
+
     /// Specification of the desired behavior of the Deployment.
     pub spec: DeploymentSpec,
 
@@ -21,6 +18,18 @@ pub struct Deployment {
     pub status: Option<DeploymentStatus>,
 }
 
+impl Default for Deployment {
+    fn default() -> Deployment {
+        Deployment {
+            types: TypeMeta::of::<Deployment>(),
+            spec: Default::default(),
+            metadata: Default::default(),
+            status: Default::default(),
+        }
+    }
+}
+
+/// The `autoscaling/v1` scale subresource of a `Scalable` workload
 #[derive(Serialize, Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Scale {
@@ -35,20 +44,6 @@ pub struct Scale {
     pub status: Option<ScaleStatus>,
 }
 
-impl Scale {
-    pub(crate) fn replicas(namespace: &str, name: &str, count: u32) -> Scale {
-        Scale {
-            spec: ScaleSpec { replicas: Some(count as i32) },
-            metadata: ObjectMeta {
-                name: Some(name.to_owned()),
-                namespace: Some(namespace.to_owned()),
-                ..Default::default()
-            },
-            ..Default::default()
-        }
-    }
-}
-
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct DeploymentList {
     items: Vec<Deployment>,
@@ -62,7 +57,25 @@ impl Deployment {
 }
 
 impl Resource for Deployment {
-    fn kind() -> Kind { Kind::Deployment }
+    const GROUP: &'static str = "apps";
+    const VERSION: &'static str = "v1";
+    const KIND: &'static str = "Deployment";
+    const PLURAL: &'static str = "deployments";
+    const NAMESPACED: bool = true;
+    fn metadata(&self) -> &ObjectMeta { &self.metadata }
+    fn metadata_mut(&mut self) -> &mut ObjectMeta { &mut self.metadata }
+}
+
+impl HasStatus for Deployment {
+    type Status = DeploymentStatus;
+    fn status(&self) -> Option<&Self::Status> { self.status.as_ref() }
+    fn status_mut(&mut self) -> &mut Option<Self::Status> { &mut self.status }
+}
+
+impl Scalable for Deployment {
+    fn ready_replicas(&self) -> i32 {
+        self.status.as_ref().and_then(|status| status.ready_replicas).unwrap_or(0)
+    }
 }
 
 impl ListableResource for Deployment {
@@ -70,4 +83,5 @@ impl ListableResource for Deployment {
     fn list_items(response: Self::ListResponse) -> Vec<Self> {
         response.items
     }
+    fn types_mut(&mut self) -> &mut TypeMeta { &mut self.types }
 }
@@ -0,0 +1,169 @@
+use super::*;
+use k8s_api::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use serde_json::{Map, Value};
+
+// The API groups served by the Kubernetes API server itself, rather than by CRDs
+// or aggregated API servers
+const BUILT_IN_GROUPS: &[&str] = &[
+    "", "admissionregistration.k8s.io", "apps", "autoscaling", "batch", "certificates.k8s.io",
+    "coordination.k8s.io", "discovery.k8s.io", "events.k8s.io", "extensions", "networking.k8s.io",
+    "node.k8s.io", "policy", "rbac.authorization.k8s.io", "scheduling.k8s.io", "storage.k8s.io",
+];
+
+/// Describes a kind only known at runtime, e.g. a custom resource without a `Resource` type
+///
+/// ## Examples
+///
+/// ```no_run
+/// # use qube::resources::ApiResource;
+/// let certificates = ApiResource::new("cert-manager.io", "v1", "Certificate", "certificates", true);
+/// assert_eq!(certificates.api_version(), "cert-manager.io/v1");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ApiResource {
+    /// The API group, empty for the core group (e.g. `Pod`)
+    pub group: String,
+    pub version: String,
+    pub kind: String,
+
+    /// The name of the kind in URLs, e.g. `certificates`
+    pub plural: String,
+
+    /// Whether resources of this kind live in a namespace
+    pub namespaced: bool,
+}
+
+impl ApiResource {
+    pub fn new(group: &str, version: &str, kind: &str, plural: &str, namespaced: bool) -> ApiResource {
+        ApiResource {
+            group: group.to_owned(),
+            version: version.to_owned(),
+            kind: kind.to_owned(),
+            plural: plural.to_owned(),
+            namespaced,
+        }
+    }
+
+    /// The `apiVersion` of resources of this kind, e.g. `v1` or `cert-manager.io/v1`
+    pub fn api_version(&self) -> String {
+        if self.group.is_empty() {
+            self.version.clone()
+        } else {
+            format!("{}/{}", self.group, self.version)
+        }
+    }
+
+    /// Whether the kind is built into Kubernetes, whose kinds alone accept strategic merge patches
+    pub fn accepts_strategic_merge(&self) -> bool {
+        BUILT_IN_GROUPS.contains(&self.group.as_str())
+    }
+
+    /// The root of the API serving this kind, e.g. `/api/v1` or `/apis/cert-manager.io/v1`
+    pub fn api(&self) -> String {
+        if self.group.is_empty() {
+            format!("/api/{}", self.version)
+        } else {
+            format!("/apis/{}/{}", self.group, self.version)
+        }
+    }
+
+    /// The path of the resources of this kind in `namespace`, e.g. `/api/v1/namespaces/default/pods`
+    ///
+    /// The namespace is ignored for cluster-scoped kinds.
+    pub fn path(&self, namespace: Option<&str>) -> String {
+        match namespace {
+            Some(ns) if self.namespaced => format!("{}/namespaces/{}/{}", self.api(), ns, self.plural),
+            _ => format!("{}/{}", self.api(), self.plural),
+        }
+    }
+}
+
+/// A resource of any kind, with its fields other than `metadata` kept as JSON
+///
+/// ## Examples
+///
+/// ```no_run
+/// # #[macro_use] extern crate serde_json;
+/// # use qube::resources::{ApiResource, DynamicObject};
+/// let certificates = ApiResource::new("cert-manager.io", "v1", "Certificate", "certificates", true);
+/// let mut certificate = DynamicObject::new("web-server", &certificates);
+/// certificate.data["spec"] = json!({ "secretName": "web-server-tls", "dnsNames": ["example.com"] });
+/// ```
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct DynamicObject {
+    #[serde(flatten)]
+    pub types: TypeMeta,
+
+    /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/api-conventions.md#metadata
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+
+    /// All the other fields, e.g. `spec` and `status`
+    #[serde(flatten)]
+    pub data: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct DynamicObjectList {
+    #[serde(default)]
+    pub(crate) items: Vec<DynamicObject>,
+}
+
+impl DynamicObject {
+    pub fn new(name: &str, resource: &ApiResource) -> DynamicObject {
+        DynamicObject {
+            types: TypeMeta {
+                api_version: Some(resource.api_version()),
+                kind: Some(resource.kind.clone()),
+            },
+            metadata: ObjectMeta{ name: Some(name.to_owned()), ..Default::default() },
+            data: Value::Object(Map::new()),
+        }
+    }
+
+    // List items and some responses omit `apiVersion` and `kind`
+    pub(crate) fn with_types(mut self, resource: &ApiResource) -> DynamicObject {
+        self.types.api_version.get_or_insert_with(|| resource.api_version());
+        self.types.kind.get_or_insert_with(|| resource.kind.clone());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{self, json};
+
+    #[test]
+    fn paths_of_core_and_grouped_kinds() {
+        let pods = ApiResource::new("", "v1", "Pod", "pods", true);
+        assert_eq!(pods.api_version(), "v1");
+        assert_eq!(pods.path(Some("default")), "/api/v1/namespaces/default/pods");
+
+        let issuers = ApiResource::new("cert-manager.io", "v1", "ClusterIssuer", "clusterissuers", false);
+        assert_eq!(issuers.api_version(), "cert-manager.io/v1");
+        assert_eq!(issuers.path(Some("default")), "/apis/cert-manager.io/v1/clusterissuers");
+    }
+
+    #[test]
+    fn dynamic_objects_keep_unknown_fields() {
+        let json = json!({
+            "apiVersion": "cert-manager.io/v1",
+            "kind": "Certificate",
+            "metadata": { "name": "web-server" },
+            "spec": { "secretName": "web-server-tls" }
+        });
+        let certificate: DynamicObject = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(certificate.data["spec"]["secretName"], "web-server-tls");
+        assert_eq!(serde_json::to_value(&certificate).unwrap(), json);
+    }
+
+    #[test]
+    fn list_items_are_given_their_types() {
+        let certificates = ApiResource::new("cert-manager.io", "v1", "Certificate", "certificates", true);
+        let item: DynamicObject = serde_json::from_value(json!({ "metadata": { "name": "web-server" } })).unwrap();
+        let item = item.with_types(&certificates);
+        assert_eq!(item.types.api_version.as_deref(), Some("cert-manager.io/v1"));
+        assert_eq!(item.types.kind.as_deref(), Some("Certificate"));
+    }
+}
@@ -0,0 +1,125 @@
+use super::*;
+use k8s_api::api::core::v1::{EventSource, ObjectReference};
+use k8s_api::apimachinery::pkg::apis::meta::v1::{ObjectMeta, Time};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    #[serde(flatten)]
+    pub types: TypeMeta,
+
+    /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/api-conventions.md#metadata
+    pub metadata: ObjectMeta,
+
+    /// The object that this event is about.
+    pub involved_object: ObjectReference,
+
+    /// This should be a short, machine understandable string that gives the reason for the transition into the object's current status.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    /// A human-readable description of the status of this operation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+
+    /// The component reporting this event. Should be a short machine understandable string.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<EventSource>,
+
+    /// The time at which the event was first recorded. (Time of server receipt is in TypeMeta.)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_timestamp: Option<Time>,
+
+    /// The time at which the most recent occurrence of this event was recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_timestamp: Option<Time>,
+
+    /// The number of times this event has occurred.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<i32>,
+
+    /// Type of this event (Normal, Warning), new types could be added in the future
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub type_: Option<String>,
+
+    /// Name of the controller that emitted this Event, e.g. `kubernetes.io/kubelet`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reporting_component: Option<String>,
+
+    /// ID of the controller instance, e.g. `kubelet-xyzf`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reporting_instance: Option<String>,
+}
+
+impl Default for Event {
+    fn default() -> Event {
+        Event {
+            types: TypeMeta::of::<Event>(),
+            metadata: Default::default(),
+            involved_object: Default::default(),
+            reason: Default::default(),
+            message: Default::default(),
+            source: Default::default(),
+            first_timestamp: Default::default(),
+            last_timestamp: Default::default(),
+            count: Default::default(),
+            type_: Default::default(),
+            reporting_component: Default::default(),
+            reporting_instance: Default::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct EventList {
+    items: Vec<Event>,
+}
+
+impl Event {
+    pub fn new(name: &str) -> Event {
+        let metadata = ObjectMeta{ name: Some(name.to_owned()), ..Default::default() };
+        Event { metadata, ..Default::default() }
+    }
+}
+
+impl Resource for Event {
+    const GROUP: &'static str = "";
+    const VERSION: &'static str = "v1";
+    const KIND: &'static str = "Event";
+    const PLURAL: &'static str = "events";
+    const NAMESPACED: bool = true;
+    fn metadata(&self) -> &ObjectMeta { &self.metadata }
+    fn metadata_mut(&mut self) -> &mut ObjectMeta { &mut self.metadata }
+}
+
+impl ListableResource for Event {
+    type ListResponse = EventList;
+    fn list_items(response: Self::ListResponse) -> Vec<Self> {
+        response.items
+    }
+    fn types_mut(&mut self) -> &mut TypeMeta { &mut self.types }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn events_serialize_with_kubernetes_field_names() {
+        let event = Event {
+            involved_object: Pod::new("web-1").object_reference(),
+            type_: Some("Warning".to_owned()),
+            reporting_component: Some("my-controller".to_owned()),
+            ..Event::new("web-1.1234")
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["apiVersion"], "v1");
+        assert_eq!(json["kind"], "Event");
+        assert_eq!(json["type"], "Warning");
+        assert_eq!(json["reportingComponent"], "my-controller");
+        assert_eq!(json["involvedObject"]["kind"], "Pod");
+        assert_eq!(json["involvedObject"]["name"], "web-1");
+        assert!(json.get("count").is_none());
+    }
+}
@@ -0,0 +1,117 @@
+use super::*;
+use chrono::{DateTime, Utc};
+use k8s_api::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+
+/// A `coordination.k8s.io` Lease
+///
+/// Leases are served as `v1` since Kubernetes 1.14 and as `v1beta1` in 1.12 and 1.13,
+/// so the version follows the selected `v1_*` feature. Clusters older than 1.12 don't
+/// serve leases at all: use a `ConfigMapLock` for leader election there.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Lease {
+    #[serde(flatten)]
+    pub types: TypeMeta,
+
+    /// Specification of the Lease. More info: https://git.k8s.io/community/contributors/devel/api-conventions.md#spec-and-status
+    #[serde(default)]
+    pub spec: LeaseSpec,
+
+    /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/api-conventions.md#metadata
+    pub metadata: ObjectMeta,
+}
+
+impl Default for Lease {
+    fn default() -> Lease {
+        Lease {
+            types: TypeMeta::of::<Lease>(),
+            spec: Default::default(),
+            metadata: Default::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaseSpec {
+    /// The identity of the holder of a current lease.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub holder_identity: Option<String>,
+
+    /// Duration that candidates for a lease need to wait to force acquire it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lease_duration_seconds: Option<i32>,
+
+    /// The time the current lease was acquired.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "micro_time")]
+    pub acquire_time: Option<DateTime<Utc>>,
+
+    /// The time the current holder of a lease has last updated the lease.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "micro_time")]
+    pub renew_time: Option<DateTime<Utc>>,
+
+    /// The number of transitions of a lease between holders.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lease_transitions: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct LeaseList {
+    items: Vec<Lease>,
+}
+
+impl Lease {
+    pub fn new(name: &str) -> Lease {
+        let metadata = ObjectMeta{ name: Some(name.to_owned()), ..Default::default() };
+        Lease { metadata, ..Default::default() }
+    }
+}
+
+#[cfg(any(feature = "v1_12", feature = "v1_13"))]
+const LEASE_VERSION: &str = "v1beta1";
+#[cfg(not(any(feature = "v1_12", feature = "v1_13")))]
+const LEASE_VERSION: &str = "v1";
+
+impl Resource for Lease {
+    const GROUP: &'static str = "coordination.k8s.io";
+    const VERSION: &'static str = LEASE_VERSION;
+    const KIND: &'static str = "Lease";
+    const PLURAL: &'static str = "leases";
+    const NAMESPACED: bool = true;
+    fn metadata(&self) -> &ObjectMeta { &self.metadata }
+    fn metadata_mut(&mut self) -> &mut ObjectMeta { &mut self.metadata }
+}
+
+impl ListableResource for Lease {
+    type ListResponse = LeaseList;
+    fn list_items(response: Self::ListResponse) -> Vec<Self> {
+        response.items
+    }
+    fn types_mut(&mut self) -> &mut TypeMeta { &mut self.types }
+}
+
+// The API server only accepts `MicroTime`s with exactly six fractional digits
+mod micro_time {
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+    use serde::de::Error;
+
+    pub fn serialize<S>(time: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer
+    {
+        match *time {
+            Some(ref time) => serializer.serialize_str(&time.format("%Y-%m-%dT%H:%M:%S%.6fZ").to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+    where D: Deserializer<'de>
+    {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(time) => DateTime::parse_from_rfc3339(&time)
+                .map(|time| Some(time.with_timezone(&Utc)))
+                .map_err(D::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
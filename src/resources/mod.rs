@@ -1,87 +1,198 @@
 mod secret;
 mod config_map;
+mod custom_resource_definition;
 mod node;
 mod daemon_set;
 mod deployment;
+mod dynamic;
 mod network_policy;
 mod pod;
+mod replica_set;
 mod service;
+mod stateful_set;
+mod lease;
+mod event;
 
 pub use self::secret::*;
 pub use self::config_map::*;
+pub use self::custom_resource_definition::*;
 pub use self::node::*;
 pub use self::daemon_set::*;
 pub use self::deployment::*;
+pub use self::dynamic::*;
 pub use self::network_policy::*;
 pub use self::pod::*;
+pub use self::replica_set::*;
 pub use self::service::*;
+pub use self::stateful_set::*;
+pub use self::lease::*;
+pub use self::event::*;
 
-use k8s_api::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_api::api::core::v1::ObjectReference;
+use k8s_api::apimachinery::pkg::apis::meta::v1::StatusDetails;
 use serde::Serialize;
 use serde::de::DeserializeOwned;
-use std::fmt;
 use std::collections::BTreeMap;
-use std::ops::Deref;
-
-pub(crate) const V1_API: &str = "/api/v1";
-pub(crate) const V1_BETA_API: &str = "/apis/extensions/v1beta1";
-
-#[derive(Serialize, Deserialize, Debug)]
-pub enum Kind { DaemonSet, Deployment, ConfigMap, NetworkPolicy, Node, Pod, Secret, Service }
-
-impl Deref for Kind {
-    type Target = KindInfo;
-    fn deref(&self) -> &KindInfo {
-        match *self {
-            Kind::ConfigMap => &CONFIG_MAP_INFO,
-            Kind::DaemonSet => &DAEMON_SET_INFO,
-            Kind::Deployment => &DEPLOYMENT_INFO,
-            Kind::NetworkPolicy => &NETWORK_POLICY_INFO,
-            Kind::Node => &NODE_INFO,
-            Kind::Pod => &POD_INFO,
-            Kind::Secret => &SECRET_INFO,
-            Kind::Service => &SERVICE_INFO,
-        }
-    }
-}
 
-#[derive(Copy, Clone, Debug)]
-pub struct KindInfo {
-    pub plural: &'static str,
-    pub default_namespace: Option<&'static str>,
-    pub api: &'static str,
+pub use k8s_api::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+
+/// The `apiVersion` and `kind` fields common to all resources
+///
+/// These are always those of the API the resource is sent to, which `TypeMeta::of` gives.
+/// Lists leave them out of their items, so they are filled in on listed resources.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeMeta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
 }
 
-// Debug output of Kind is exactly what we want for Display
-impl fmt::Display for Kind {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Debug::fmt(self, f)
+impl TypeMeta {
+    /// The `apiVersion` and `kind` of a resource type
+    pub fn of<R: Resource>() -> TypeMeta {
+        TypeMeta { api_version: Some(R::api_version()), kind: Some(R::KIND.to_owned()) }
     }
 }
 
+/// A kind of resource that `KubeClient` can manage
+///
+/// Implement it for your own types, e.g. the structs of a custom resource,
+/// to manage them with `Kubernetes::client`, or derive it with `qube-derive`.
+///
+/// ## Examples
+///
+/// ```no_run
+/// # #[macro_use] extern crate serde_derive;
+/// # use qube::prelude::*;
+/// # use qube::resources::{ObjectMeta, Resource};
+/// #[derive(Serialize, Deserialize)]
+/// struct Certificate {
+///     metadata: ObjectMeta,
+///     spec: serde_json::Value,
+/// }
+///
+/// impl Resource for Certificate {
+///     const GROUP: &'static str = "cert-manager.io";
+///     const VERSION: &'static str = "v1";
+///     const KIND: &'static str = "Certificate";
+///     const PLURAL: &'static str = "certificates";
+///     const NAMESPACED: bool = true;
+///     fn metadata(&self) -> &ObjectMeta { &self.metadata }
+///     fn metadata_mut(&mut self) -> &mut ObjectMeta { &mut self.metadata }
+/// }
+///
+/// let kube = Kubernetes::load_conf("admin.conf")?;
+/// let certificate = kube.client::<Certificate>().get("web-server")?;
+/// # Ok::<(), qube::Error>(())
+/// ```
 pub trait Resource: Serialize + DeserializeOwned {
-    fn kind() -> Kind;
-    fn api() -> &'static str {
-        Self::kind().api
+    /// The API group, e.g. `apps`, or empty for the core group
+    const GROUP: &'static str;
+    /// The version within the group, e.g. `v1`
+    const VERSION: &'static str;
+    /// The kind, e.g. `Deployment`
+    const KIND: &'static str;
+    /// The lowercase plural name used in URLs, e.g. `deployments`
+    const PLURAL: &'static str;
+    /// Whether resources live in a namespace, or are cluster-scoped like nodes
+    const NAMESPACED: bool;
+
+    fn metadata(&self) -> &ObjectMeta;
+    fn metadata_mut(&mut self) -> &mut ObjectMeta;
+
+    /// The path the API of the resource is served at, e.g. `/apis/apps/v1`
+    fn api() -> String {
+        if Self::GROUP.is_empty() {
+            format!("/api/{}", Self::VERSION)
+        } else {
+            format!("/apis/{}/{}", Self::GROUP, Self::VERSION)
+        }
     }
     fn default_namespace() -> Option<&'static str> {
-        Self::kind().default_namespace
+        if Self::NAMESPACED { Some("default") } else { None }
+    }
+    /// The `apiVersion` of the resource, e.g. `v1` or `apps/v1`
+    fn api_version() -> String {
+        if Self::GROUP.is_empty() {
+            Self::VERSION.to_owned()
+        } else {
+            format!("{}/{}", Self::GROUP, Self::VERSION)
+        }
+    }
+    /// Describes the kind for the dynamic client, see `Kubernetes::dynamic`
+    fn api_resource() -> ApiResource {
+        ApiResource::new(Self::GROUP, Self::VERSION, Self::KIND, Self::PLURAL, Self::NAMESPACED)
+    }
+    /// A reference to this resource, e.g. for the `involvedObject` of an `Event`
+    fn object_reference(&self) -> ObjectReference {
+        let metadata = self.metadata();
+        ObjectReference {
+            api_version: Some(Self::api_version()),
+            kind: Some(Self::KIND.to_owned()),
+            name: metadata.name.clone(),
+            namespace: metadata.namespace.clone(),
+            uid: metadata.uid.clone(),
+            resource_version: metadata.resource_version.clone(),
+            field_path: None,
+        }
     }
 }
 
+/// A resource with a `status`, written through its `/status` subresource
+pub trait HasStatus: Resource {
+    type Status: Serialize + DeserializeOwned;
+    fn status(&self) -> Option<&Self::Status>;
+    fn status_mut(&mut self) -> &mut Option<Self::Status>;
+}
+
+/// A workload with a `/scale` subresource, e.g. a `Deployment`
+///
+/// Custom resources declaring a scale subresource can implement it too.
+pub trait Scalable: Resource {
+    /// The number of ready replicas, as last reported in the resource's status
+    fn ready_replicas(&self) -> i32;
+}
+
 pub trait ListableResource: Resource {
     type ListResponse: DeserializeOwned;
     fn list_items(response: Self::ListResponse) -> Vec<Self>;
+    fn types_mut(&mut self) -> &mut TypeMeta;
+
+    /// The items of a list, with the `apiVersion` and `kind` that lists leave out
+    fn typed_list_items(response: Self::ListResponse) -> Vec<Self> {
+        let mut items = Self::list_items(response);
+        for item in &mut items {
+            *item.types_mut() = TypeMeta::of::<Self>();
+        }
+        items
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
 pub struct Status {
     pub kind: String,
     pub api_version: String,
     pub metadata: ObjectMeta,
     pub status: String,
     pub message: String,
+    pub reason: Option<String>,
+    pub code: Option<u16>,
+    pub details: Option<StatusDetails>,
+}
+
+/// A single change notification received from a kubernetes watch
+#[allow(clippy::large_enum_variant)]
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", content = "object", rename_all = "UPPERCASE")]
+pub enum WatchEvent<R> {
+    Added(R),
+    Modified(R),
+    Deleted(R),
+    Error(Status),
 }
 
 #[derive(Clone, Debug, Default)]
@@ -90,6 +201,8 @@ pub struct ListQuery {
     label_selector: Option<String>,
     resource_version: Option<String>,
     timeout_seconds: Option<String>,
+    limit: Option<String>,
+    continue_token: Option<String>,
 }
 
 impl ListQuery {
@@ -107,6 +220,12 @@ impl ListQuery {
         if let Some(ref ts) = self.timeout_seconds {
             map.insert("timeoutSeconds", ts.to_owned());
         }
+        if let Some(ref limit) = self.limit {
+            map.insert("limit", limit.to_owned());
+        }
+        if let Some(ref token) = self.continue_token {
+            map.insert("continue", token.to_owned());
+        }
         map
     }
 
@@ -131,4 +250,61 @@ impl ListQuery {
         new.timeout_seconds = Some(timeout_seconds.to_string());
         new
     }
+    /// The maximum number of resources returned by a single list request
+    pub fn limit(&self, limit: u32) -> Self {
+        let mut new = self.clone();
+        new.limit = Some(limit.to_string());
+        new
+    }
+    /// Continues a paginated list from the `continue` token of the previous page
+    pub fn continue_token<S: Into<String>>(&self, continue_token: S) -> Self {
+        let mut new = self.clone();
+        new.continue_token = Some(continue_token.into());
+        new
+    }
+    pub(crate) fn has_limit(&self) -> bool {
+        self.limit.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{self, json};
+
+    #[test]
+    fn core_and_grouped_kinds() {
+        assert_eq!(Pod::api(), "/api/v1");
+        assert_eq!(Pod::api_version(), "v1");
+        assert_eq!(StatefulSet::api(), "/apis/apps/v1");
+        assert_eq!(StatefulSet::api_version(), "apps/v1");
+    }
+
+    #[test]
+    fn only_namespaced_kinds_have_a_default_namespace() {
+        assert_eq!(Pod::default_namespace(), Some("default"));
+        assert_eq!(Node::default_namespace(), None);
+    }
+
+    #[test]
+    fn object_references_name_the_kind() {
+        let mut pod = Pod::new("web-0");
+        pod.metadata.namespace = Some("prod".to_owned());
+        let reference = pod.object_reference();
+        assert_eq!(reference.api_version.as_deref(), Some("v1"));
+        assert_eq!(reference.kind.as_deref(), Some("Pod"));
+        assert_eq!(reference.name.as_deref(), Some("web-0"));
+        assert_eq!(reference.namespace.as_deref(), Some("prod"));
+    }
+
+    #[test]
+    fn list_items_are_given_their_types() {
+        let list: PodList = serde_json::from_value(json!({
+            "apiVersion": "v1",
+            "kind": "PodList",
+            "items": [{ "metadata": { "name": "web-0" }, "spec": { "containers": [] } }]
+        })).unwrap();
+        let pods = Pod::typed_list_items(list);
+        assert_eq!(pods[0].types, TypeMeta::of::<Pod>());
+    }
 }
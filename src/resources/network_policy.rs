@@ -2,14 +2,11 @@ use super::*;
 use k8s_api::api::networking::v1::NetworkPolicySpec;
 use k8s_api::apimachinery::pkg::apis::meta::v1::ObjectMeta;
 
-pub(crate) static NETWORK_POLICY_INFO: KindInfo = KindInfo {
-    plural: "networkpolicies",
-    default_namespace: Some("default"),
-    api: V1_BETA_API,
-};
-
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct NetworkPolicy {
+    #[serde(flatten)]
+    pub types: TypeMeta,
+
     /// Specification of the desired behavior for this NetworkPolicy.
     pub spec: NetworkPolicySpec,
 
@@ -17,6 +14,16 @@ pub struct NetworkPolicy {
     pub metadata: ObjectMeta,
 }
 
+impl Default for NetworkPolicy {
+    fn default() -> NetworkPolicy {
+        NetworkPolicy {
+            types: TypeMeta::of::<NetworkPolicy>(),
+            spec: Default::default(),
+            metadata: Default::default(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct NetworkPolicyList {
     items: Vec<NetworkPolicy>,
@@ -31,7 +38,13 @@ impl NetworkPolicy {
 }
 
 impl Resource for NetworkPolicy {
-    fn kind() -> Kind { Kind::NetworkPolicy }
+    const GROUP: &'static str = "networking.k8s.io";
+    const VERSION: &'static str = "v1";
+    const KIND: &'static str = "NetworkPolicy";
+    const PLURAL: &'static str = "networkpolicies";
+    const NAMESPACED: bool = true;
+    fn metadata(&self) -> &ObjectMeta { &self.metadata }
+    fn metadata_mut(&mut self) -> &mut ObjectMeta { &mut self.metadata }
 }
 
 
@@ -40,4 +53,5 @@ impl ListableResource for NetworkPolicy {
     fn list_items(response: Self::ListResponse) -> Vec<Self> {
         response.items
     }
+    fn types_mut(&mut self) -> &mut TypeMeta { &mut self.types }
 }
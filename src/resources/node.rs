@@ -2,14 +2,11 @@ use super::*;
 use k8s_api::api::core::v1::{NodeSpec, NodeStatus};
 use k8s_api::apimachinery::pkg::apis::meta::v1::ObjectMeta;
 
-pub(crate) static NODE_INFO: KindInfo = KindInfo {
-    plural: "nodes",
-    default_namespace: None,
-    api: V1_API,
-};
-
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Node {
+    #[serde(flatten)]
+    pub types: TypeMeta,
+
     /// Spec defines the behavior of a node. https://git.k8s.io/community/contributors/devel/api-conventions.md#spec-and-status
     pub spec: NodeSpec,
 
@@ -22,6 +19,17 @@ pub struct Node {
     pub status: Option<NodeStatus>,
 }
 
+impl Default for Node {
+    fn default() -> Node {
+        Node {
+            types: TypeMeta::of::<Node>(),
+            spec: Default::default(),
+            metadata: Default::default(),
+            status: Default::default(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct NodeList {
     items: Vec<Node>,
@@ -35,7 +43,19 @@ impl Node {
 }
 
 impl Resource for Node {
-    fn kind() -> Kind { Kind::Node }
+    const GROUP: &'static str = "";
+    const VERSION: &'static str = "v1";
+    const KIND: &'static str = "Node";
+    const PLURAL: &'static str = "nodes";
+    const NAMESPACED: bool = false;
+    fn metadata(&self) -> &ObjectMeta { &self.metadata }
+    fn metadata_mut(&mut self) -> &mut ObjectMeta { &mut self.metadata }
+}
+
+impl HasStatus for Node {
+    type Status = NodeStatus;
+    fn status(&self) -> Option<&Self::Status> { self.status.as_ref() }
+    fn status_mut(&mut self) -> &mut Option<Self::Status> { &mut self.status }
 }
 
 impl ListableResource for Node {
@@ -43,4 +63,5 @@ impl ListableResource for Node {
     fn list_items(response: Self::ListResponse) -> Vec<Self> {
         response.items
     }
+    fn types_mut(&mut self) -> &mut TypeMeta { &mut self.types }
 }
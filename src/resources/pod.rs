@@ -2,14 +2,11 @@ use super::*;
 use k8s_api::api::core::v1::{PodSpec, PodStatus};
 use k8s_api::apimachinery::pkg::apis::meta::v1::ObjectMeta;
 
-pub(crate) static POD_INFO: KindInfo = KindInfo {
-    plural: "pods",
-    default_namespace: Some("default"),
-    api: V1_API,
-};
-
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Pod {
+    #[serde(flatten)]
+    pub types: TypeMeta,
+
     /// Specification of the desired behavior of the pod. More info: https://git.k8s.io/community/contributors/devel/api-conventions.md#spec-and-status
     pub spec: PodSpec,
 
@@ -21,6 +18,17 @@ pub struct Pod {
     pub status: Option<PodStatus>,
 }
 
+impl Default for Pod {
+    fn default() -> Pod {
+        Pod {
+            types: TypeMeta::of::<Pod>(),
+            spec: Default::default(),
+            metadata: Default::default(),
+            status: Default::default(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct PodList {
     items: Vec<Pod>,
@@ -76,7 +84,19 @@ impl Pod {
 }
 
 impl Resource for Pod {
-    fn kind() -> Kind { Kind::Pod }
+    const GROUP: &'static str = "";
+    const VERSION: &'static str = "v1";
+    const KIND: &'static str = "Pod";
+    const PLURAL: &'static str = "pods";
+    const NAMESPACED: bool = true;
+    fn metadata(&self) -> &ObjectMeta { &self.metadata }
+    fn metadata_mut(&mut self) -> &mut ObjectMeta { &mut self.metadata }
+}
+
+impl HasStatus for Pod {
+    type Status = PodStatus;
+    fn status(&self) -> Option<&Self::Status> { self.status.as_ref() }
+    fn status_mut(&mut self) -> &mut Option<Self::Status> { &mut self.status }
 }
 
 
@@ -85,4 +105,5 @@ impl ListableResource for Pod {
     fn list_items(response: Self::ListResponse) -> Vec<Self> {
         response.items
     }
+    fn types_mut(&mut self) -> &mut TypeMeta { &mut self.types }
 }
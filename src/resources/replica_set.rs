@@ -0,0 +1,71 @@
+use super::*;
+use k8s_api::api::apps::v1::{ReplicaSetSpec, ReplicaSetStatus};
+use k8s_api::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReplicaSet {
+    #[serde(flatten)]
+    pub types: TypeMeta,
+
+    /// Spec defines the specification of the desired behavior of the ReplicaSet.
+    pub spec: ReplicaSetSpec,
+
+    /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/api-conventions.md#metadata
+    pub metadata: ObjectMeta,
+
+    /// Status is the most recently observed status of the ReplicaSet. Populated by the system. Read-only.
+    pub status: Option<ReplicaSetStatus>,
+}
+
+impl Default for ReplicaSet {
+    fn default() -> ReplicaSet {
+        ReplicaSet {
+            types: TypeMeta::of::<ReplicaSet>(),
+            spec: Default::default(),
+            metadata: Default::default(),
+            status: Default::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ReplicaSetList {
+    items: Vec<ReplicaSet>,
+}
+
+impl ReplicaSet {
+    pub fn new(name: &str) -> ReplicaSet {
+        let metadata = ObjectMeta{ name: Some(name.to_owned()), ..Default::default() };
+        ReplicaSet { metadata, ..Default::default() }
+    }
+}
+
+impl Resource for ReplicaSet {
+    const GROUP: &'static str = "apps";
+    const VERSION: &'static str = "v1";
+    const KIND: &'static str = "ReplicaSet";
+    const PLURAL: &'static str = "replicasets";
+    const NAMESPACED: bool = true;
+    fn metadata(&self) -> &ObjectMeta { &self.metadata }
+    fn metadata_mut(&mut self) -> &mut ObjectMeta { &mut self.metadata }
+}
+
+impl HasStatus for ReplicaSet {
+    type Status = ReplicaSetStatus;
+    fn status(&self) -> Option<&Self::Status> { self.status.as_ref() }
+    fn status_mut(&mut self) -> &mut Option<Self::Status> { &mut self.status }
+}
+
+impl Scalable for ReplicaSet {
+    fn ready_replicas(&self) -> i32 {
+        self.status.as_ref().and_then(|status| status.ready_replicas).unwrap_or(0)
+    }
+}
+
+impl ListableResource for ReplicaSet {
+    type ListResponse = ReplicaSetList;
+    fn list_items(response: Self::ListResponse) -> Vec<Self> {
+        response.items
+    }
+    fn types_mut(&mut self) -> &mut TypeMeta { &mut self.types }
+}
@@ -3,14 +3,11 @@ use std::collections::BTreeMap;
 use base64;
 use k8s_api::apimachinery::pkg::apis::meta::v1::ObjectMeta;
 
-pub(crate) static SECRET_INFO: KindInfo = KindInfo {
-    plural: "secrets",
-    default_namespace: Some("default"),
-    api: V1_API,
-};
-
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Secret {
+    #[serde(flatten)]
+    pub types: TypeMeta,
+
     data: BTreeMap<String, String>,
     metadata: ObjectMeta,
 }
@@ -19,7 +16,7 @@ impl Secret {
     pub fn new(name: &str) -> Secret {
         let data = BTreeMap::new();
         let metadata = ObjectMeta{ name: Some(name.to_owned()), ..Default::default() };
-        Secret { data, metadata }
+        Secret { types: TypeMeta::of::<Secret>(), data, metadata }
     }
 
     pub fn insert<K,V>(&mut self, name: K, secret: V) -> &mut Secret
@@ -51,5 +48,11 @@ impl Secret {
 }
 
 impl Resource for Secret {
-    fn kind() -> Kind { Kind::Secret }
+    const GROUP: &'static str = "";
+    const VERSION: &'static str = "v1";
+    const KIND: &'static str = "Secret";
+    const PLURAL: &'static str = "secrets";
+    const NAMESPACED: bool = true;
+    fn metadata(&self) -> &ObjectMeta { &self.metadata }
+    fn metadata_mut(&mut self) -> &mut ObjectMeta { &mut self.metadata }
 }
@@ -2,14 +2,11 @@ use super::*;
 use k8s_api::api::core::v1::{ServiceSpec, ServiceStatus};
 use k8s_api::apimachinery::pkg::apis::meta::v1::ObjectMeta;
 
-pub(crate) static SERVICE_INFO: KindInfo = KindInfo {
-    plural: "services",
-    default_namespace: Some("default"),
-    api: V1_API,
-};
-
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Service {
+    #[serde(flatten)]
+    pub types: TypeMeta,
+
     /// Spec defines the behavior of a service. https://git.k8s.io/community/contributors/devel/api-conventions.md#spec-and-status
     pub spec: ServiceSpec,
 
@@ -21,6 +18,17 @@ pub struct Service {
     pub status: Option<ServiceStatus>,
 }
 
+impl Default for Service {
+    fn default() -> Service {
+        Service {
+            types: TypeMeta::of::<Service>(),
+            spec: Default::default(),
+            metadata: Default::default(),
+            status: Default::default(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct ServiceList {
     items: Vec<Service>,
@@ -34,7 +42,19 @@ impl Service {
 }
 
 impl Resource for Service {
-    fn kind() -> Kind { Kind::Service }
+    const GROUP: &'static str = "";
+    const VERSION: &'static str = "v1";
+    const KIND: &'static str = "Service";
+    const PLURAL: &'static str = "services";
+    const NAMESPACED: bool = true;
+    fn metadata(&self) -> &ObjectMeta { &self.metadata }
+    fn metadata_mut(&mut self) -> &mut ObjectMeta { &mut self.metadata }
+}
+
+impl HasStatus for Service {
+    type Status = ServiceStatus;
+    fn status(&self) -> Option<&Self::Status> { self.status.as_ref() }
+    fn status_mut(&mut self) -> &mut Option<Self::Status> { &mut self.status }
 }
 
 
@@ -43,4 +63,5 @@ impl ListableResource for Service {
     fn list_items(response: Self::ListResponse) -> Vec<Self> {
         response.items
     }
+    fn types_mut(&mut self) -> &mut TypeMeta { &mut self.types }
 }
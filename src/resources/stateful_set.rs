@@ -0,0 +1,85 @@
+use super::*;
+use k8s_api::api::apps::v1::{StatefulSetSpec, StatefulSetStatus};
+use k8s_api::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StatefulSet {
+    #[serde(flatten)]
+    pub types: TypeMeta,
+
+    /// Spec defines the desired identities of pods in this set.
+    pub spec: StatefulSetSpec,
+
+    /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/api-conventions.md#metadata
+    pub metadata: ObjectMeta,
+
+    /// Status is the current status of Pods in this StatefulSet. This data may be out of date by some window of time.
+    pub status: Option<StatefulSetStatus>,
+}
+
+impl Default for StatefulSet {
+    fn default() -> StatefulSet {
+        StatefulSet {
+            types: TypeMeta::of::<StatefulSet>(),
+            spec: Default::default(),
+            metadata: Default::default(),
+            status: Default::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct StatefulSetList {
+    items: Vec<StatefulSet>,
+}
+
+impl StatefulSet {
+    pub fn new(name: &str) -> StatefulSet {
+        let metadata = ObjectMeta{ name: Some(name.to_owned()), ..Default::default() };
+        StatefulSet { metadata, ..Default::default() }
+    }
+}
+
+impl Resource for StatefulSet {
+    const GROUP: &'static str = "apps";
+    const VERSION: &'static str = "v1";
+    const KIND: &'static str = "StatefulSet";
+    const PLURAL: &'static str = "statefulsets";
+    const NAMESPACED: bool = true;
+    fn metadata(&self) -> &ObjectMeta { &self.metadata }
+    fn metadata_mut(&mut self) -> &mut ObjectMeta { &mut self.metadata }
+}
+
+impl HasStatus for StatefulSet {
+    type Status = StatefulSetStatus;
+    fn status(&self) -> Option<&Self::Status> { self.status.as_ref() }
+    fn status_mut(&mut self) -> &mut Option<Self::Status> { &mut self.status }
+}
+
+impl Scalable for StatefulSet {
+    fn ready_replicas(&self) -> i32 {
+        self.status.as_ref().and_then(|status| status.ready_replicas).unwrap_or(0)
+    }
+}
+
+impl ListableResource for StatefulSet {
+    type ListResponse = StatefulSetList;
+    fn list_items(response: Self::ListResponse) -> Vec<Self> {
+        response.items
+    }
+    fn types_mut(&mut self) -> &mut TypeMeta { &mut self.types }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ready_replicas_default_to_zero() {
+        let mut stateful_set = StatefulSet::new("database");
+        assert_eq!(stateful_set.ready_replicas(), 0);
+
+        stateful_set.status = Some(StatefulSetStatus { replicas: 3, ready_replicas: Some(2), ..Default::default() });
+        assert_eq!(stateful_set.ready_replicas(), 2);
+    }
+}
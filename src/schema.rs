@@ -0,0 +1,187 @@
+//! OpenAPI v3 schemas of Rust types, for the CRDs of custom resources
+//!
+//! Schemas are structural, as Kubernetes requires of CRDs: every node has a `type`
+//! and nested types are inlined rather than referenced.
+//!
+//! `#[derive(JsonSchema)]` from `qube-derive` implements `JsonSchema` for structs
+//! and unit enums, following their serde attributes (`rename`, `rename_all`, `skip`,
+//! `default` and `flatten`) and turning doc comments into descriptions.
+
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// An OpenAPI v3 schema, i.e. a `JSONSchemaProps` object
+pub type Schema = Value;
+
+/// A type with an OpenAPI v3 schema
+///
+/// Recursive types can't implement it, as their schemas would be infinite.
+pub trait JsonSchema {
+    fn schema() -> Schema;
+}
+
+fn typed(type_: &str, format: Option<&str>) -> Schema {
+    let mut schema = Map::new();
+    schema.insert("type".to_owned(), Value::from(type_));
+    if let Some(format) = format {
+        schema.insert("format".to_owned(), Value::from(format));
+    }
+    Value::Object(schema)
+}
+
+macro_rules! impl_schema {
+    ($type_:expr, $format:expr => $($t:ty),*) => {
+        $(
+            impl JsonSchema for $t {
+                fn schema() -> Schema {
+                    typed($type_, $format)
+                }
+            }
+        )*
+    };
+}
+
+impl_schema!("boolean", None => bool);
+impl_schema!("string", None => String, str, char);
+impl_schema!("integer", Some("int32") => i8, i16, i32, u8, u16, u32);
+impl_schema!("integer", Some("int64") => i64, u64, isize, usize);
+impl_schema!("number", Some("float") => f32);
+impl_schema!("number", Some("double") => f64);
+impl_schema!("string", Some("date-time") => DateTime<Utc>);
+
+impl<T: JsonSchema + ?Sized> JsonSchema for &T {
+    fn schema() -> Schema {
+        T::schema()
+    }
+}
+
+impl<T: JsonSchema + ?Sized> JsonSchema for Box<T> {
+    fn schema() -> Schema {
+        T::schema()
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Option<T> {
+    fn schema() -> Schema {
+        let mut schema = T::schema();
+        if let Value::Object(ref mut fields) = schema {
+            fields.insert("nullable".to_owned(), Value::Bool(true));
+        }
+        schema
+    }
+}
+
+fn array<T: JsonSchema>() -> Schema {
+    let mut schema = typed("array", None);
+    schema["items"] = T::schema();
+    schema
+}
+
+impl<T: JsonSchema> JsonSchema for Vec<T> {
+    fn schema() -> Schema {
+        array::<T>()
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for BTreeSet<T> {
+    fn schema() -> Schema {
+        array::<T>()
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for HashSet<T> {
+    fn schema() -> Schema {
+        array::<T>()
+    }
+}
+
+fn map<T: JsonSchema>() -> Schema {
+    let mut schema = typed("object", None);
+    schema["additionalProperties"] = T::schema();
+    schema
+}
+
+impl<T: JsonSchema> JsonSchema for BTreeMap<String, T> {
+    fn schema() -> Schema {
+        map::<T>()
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for HashMap<String, T> {
+    fn schema() -> Schema {
+        map::<T>()
+    }
+}
+
+/// Any JSON, which Kubernetes then stores as is
+impl JsonSchema for Value {
+    fn schema() -> Schema {
+        let mut schema = Map::new();
+        schema.insert("x-kubernetes-preserve-unknown-fields".to_owned(), Value::Bool(true));
+        Value::Object(schema)
+    }
+}
+
+/// Builds the schema of a struct, as used by `#[derive(JsonSchema)]`
+#[derive(Debug, Default)]
+pub struct ObjectSchema {
+    description: Option<String>,
+    properties: Map<String, Value>,
+    required: Vec<String>,
+}
+
+impl ObjectSchema {
+    pub fn new(description: Option<&str>) -> ObjectSchema {
+        ObjectSchema { description: description.map(str::to_owned), ..Default::default() }
+    }
+
+    /// Adds a field, required unless it is an `Option` or has a default
+    pub fn property<T: JsonSchema + ?Sized>(mut self, name: &str, description: Option<&str>, required: bool) -> Self {
+        let mut schema = T::schema();
+        if let (Some(description), Value::Object(ref mut fields)) = (description, &mut schema) {
+            fields.insert("description".to_owned(), Value::from(description));
+        }
+        if required && schema.get("nullable") != Some(&Value::Bool(true)) {
+            self.required.push(name.to_owned());
+        }
+        self.properties.insert(name.to_owned(), schema);
+        self
+    }
+
+    /// Adds the fields of a `#[serde(flatten)]` field
+    pub fn flatten<T: JsonSchema + ?Sized>(mut self) -> Self {
+        let mut schema = T::schema();
+        if let Some(Value::Object(properties)) = schema.get_mut("properties").map(Value::take) {
+            self.properties.extend(properties);
+        }
+        if let Some(Value::Array(required)) = schema.get("required") {
+            self.required.extend(required.iter().filter_map(Value::as_str).map(str::to_owned));
+        }
+        self
+    }
+
+    pub fn build(self) -> Schema {
+        let mut schema = typed("object", None);
+        if let Some(description) = self.description {
+            schema["description"] = Value::from(description);
+        }
+        if !self.properties.is_empty() {
+            schema["properties"] = Value::Object(self.properties);
+        }
+        if !self.required.is_empty() {
+            schema["required"] = Value::from(self.required);
+        }
+        schema
+    }
+}
+
+/// The schema of an enum of unit variants, serialized as strings
+pub fn string_enum(description: Option<&str>, variants: &[&str]) -> Schema {
+    let mut schema = typed("string", None);
+    if let Some(description) = description {
+        schema["description"] = Value::from(description);
+    }
+    schema["enum"] = Value::from(variants.to_vec());
+    schema
+}
@@ -0,0 +1,100 @@
+#[macro_use] extern crate serde_derive;
+#[macro_use] extern crate serde_json;
+#[macro_use] extern crate qube_derive;
+extern crate qube;
+
+use qube::resources::{CustomResourceExt, Resource};
+use qube::schema::JsonSchema;
+
+/// Backs up a database on a schedule
+#[derive(CustomResource, JsonSchema, Serialize, Deserialize, Clone, Debug)]
+#[resource(group = "example.com", version = "v1", kind = "Backup", namespaced, status = "BackupStatus", scale)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSpec {
+    /// When to run the backup, in cron syntax
+    pub schedule: String,
+    pub replicas: Option<i32>,
+    pub retention_days: Option<u32>,
+    pub policy: RetryPolicy,
+    #[serde(flatten)]
+    pub target: Target,
+}
+
+#[derive(JsonSchema, Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Target {
+    pub database_name: String,
+}
+
+#[derive(JsonSchema, Serialize, Deserialize, Clone, Debug)]
+pub enum RetryPolicy {
+    Never,
+    OnFailure,
+}
+
+#[derive(JsonSchema, Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupStatus {
+    pub replicas: i32,
+    pub ready_replicas: Option<i32>,
+}
+
+#[test]
+fn spec_schemas_follow_serde() {
+    assert_eq!(BackupSpec::schema(), json!({
+        "type": "object",
+        "description": "Backs up a database on a schedule",
+        "properties": {
+            "schedule": { "type": "string", "description": "When to run the backup, in cron syntax" },
+            "replicas": { "type": "integer", "format": "int32", "nullable": true },
+            "retentionDays": { "type": "integer", "format": "int32", "nullable": true },
+            "policy": { "type": "string", "enum": ["Never", "OnFailure"] },
+            "databaseName": { "type": "string" }
+        },
+        "required": ["schedule", "policy", "databaseName"]
+    }));
+}
+
+#[test]
+fn crds_describe_the_kind() {
+    let crd = serde_json::to_value(Backup::crd()).unwrap();
+    assert_eq!(crd["apiVersion"], "apiextensions.k8s.io/v1");
+    assert_eq!(crd["kind"], "CustomResourceDefinition");
+    assert_eq!(crd["metadata"]["name"], "backups.example.com");
+    assert_eq!(crd["spec"]["group"], "example.com");
+    assert_eq!(crd["spec"]["scope"], "Namespaced");
+    assert_eq!(crd["spec"]["names"]["kind"], "Backup");
+    assert_eq!(crd["spec"]["names"]["plural"], "backups");
+
+    let version = &crd["spec"]["versions"][0];
+    assert_eq!(version["name"], "v1");
+    assert_eq!(version["served"], true);
+    assert_eq!(version["storage"], true);
+
+    let schema = &version["schema"]["openAPIV3Schema"];
+    assert_eq!(schema["type"], "object");
+    assert_eq!(schema["properties"]["spec"], BackupSpec::schema());
+    assert_eq!(schema["properties"]["status"], BackupStatus::schema());
+    assert_eq!(schema["properties"]["status"]["required"], json!(["replicas"]));
+    assert_eq!(schema["properties"]["status"]["properties"]["readyReplicas"]["nullable"], true);
+
+    assert_eq!(version["subresources"], json!({
+        "status": {},
+        "scale": { "specReplicasPath": ".spec.replicas", "statusReplicasPath": ".status.replicas" }
+    }));
+}
+
+#[test]
+fn new_resources_carry_their_types() {
+    let spec = BackupSpec {
+        schedule: "0 3 * * *".to_owned(),
+        replicas: Some(1),
+        retention_days: None,
+        policy: RetryPolicy::OnFailure,
+        target: Target { database_name: "orders".to_owned() },
+    };
+    let backup = serde_json::to_value(Backup::new("nightly", spec)).unwrap();
+    assert_eq!(backup["apiVersion"], Backup::api_version());
+    assert_eq!(backup["kind"], "Backup");
+    assert_eq!(backup["spec"]["databaseName"], "orders");
+}